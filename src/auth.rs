@@ -0,0 +1,192 @@
+use argon2::{
+    password_hash::{rand_core::{OsRng, RngCore}, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use axum::{
+    extract::{FromRequestParts, Request},
+    http::{header::AUTHORIZATION, request::Parts, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use postgres::{Client, NoTls};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::env;
+use tokio::task;
+use token_analyser::db::{api_tokens, sessions, users};
+
+// The user and how they signed in.
+#[derive(Clone, Copy)]
+pub struct AuthUser(pub i32, pub Credential);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Credential {
+    Session,
+    ApiToken(Scope),
+}
+
+impl Credential {
+    pub fn scope(self) -> Scope {
+        match self {
+            Credential::Session => Scope::ReadWrite,
+            Credential::ApiToken(scope) => scope,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Scope {
+    Read,
+    ReadWrite,
+}
+
+impl Scope {
+    pub fn parse(scope: &str) -> Option<Scope> {
+        match scope {
+            "read" => Some(Scope::Read),
+            "read_write" => Some(Scope::ReadWrite),
+            _ => None,
+        }
+    }
+}
+
+pub async fn authenticate(mut request: Request, next: Next) -> Response {
+    let Some(token) = bearer_token(request.headers()) else {
+        return error_response(StatusCode::UNAUTHORIZED, "Missing bearer token");
+    };
+    let token_hash = hash_token(token);
+
+    let identity = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(user_id) = sessions::get_user_for_session(&mut client, &token_hash).unwrap() {
+            return Some(AuthUser(user_id, Credential::Session));
+        }
+
+        api_tokens::get_user_for_api_token(&mut client, &token_hash)
+            .unwrap()
+            .and_then(|(user_id, scope)| Some(AuthUser(user_id, Credential::ApiToken(Scope::parse(&scope)?))))
+    }).await.unwrap();
+
+    let Some(auth_user) = identity else {
+        return error_response(StatusCode::UNAUTHORIZED, "Invalid or expired token");
+    };
+
+    request.extensions_mut().insert(auth_user);
+
+    next.run(request).await
+}
+
+pub async fn require_write(request: Request, next: Next) -> Response {
+    let scope = request.extensions().get::<AuthUser>().map(|AuthUser(_, credential)| credential.scope());
+    if scope != Some(Scope::ReadWrite) {
+        return error_response(StatusCode::FORBIDDEN, "Token does not allow write access");
+    }
+
+    next.run(request).await
+}
+
+// API tokens only reach the vocabulary; managing the account, its tokens or
+// the instance needs a password login.
+pub async fn require_session(request: Request, next: Next) -> Response {
+    if !matches!(request.extensions().get::<AuthUser>(), Some(AuthUser(_, Credential::Session))) {
+        return error_response(StatusCode::FORBIDDEN, "This route requires signing in with a password");
+    }
+
+    next.run(request).await
+}
+
+pub async fn require_admin(request: Request, next: Next) -> Response {
+    let Some(&AuthUser(user_id, _)) = request.extensions().get::<AuthUser>() else {
+        return error_response(StatusCode::UNAUTHORIZED, "Not authenticated");
+    };
+
+    let is_admin = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        users::is_admin(&mut client, user_id).unwrap()
+    }).await.unwrap();
+
+    if !is_admin {
+        return error_response(StatusCode::FORBIDDEN, "Administrator access required");
+    }
+
+    next.run(request).await
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthUser {
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthUser>()
+            .copied()
+            .ok_or_else(|| error_response(StatusCode::UNAUTHORIZED, "Not authenticated"))
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, Json(json!({ "success": false, "message": message }))).into_response()
+}
+
+pub fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+}
+
+pub fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .unwrap()
+        .to_string()
+}
+
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    let Ok(parsed) = PasswordHash::new(password_hash) else {
+        return false;
+    };
+    Argon2::default().verify_password(password.as_bytes(), &parsed).is_ok()
+}
+
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_password() {
+        let password_hash = hash_password("correct horse");
+        assert!(verify_password("correct horse", &password_hash));
+        assert!(!verify_password("wrong horse", &password_hash));
+        assert!(!verify_password("correct horse", "not a hash"));
+    }
+
+    #[test]
+    fn test_hash_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_ne!(hash_token(&token), token);
+    }
+}
@@ -0,0 +1,448 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::db::annotations::{self, Annotation};
+use crate::db::error::UpdateError;
+use crate::db::{languages, profiles, texts, users, vocab_user, word_lists, words};
+
+pub const BACKUP_VERSION: u32 = 2;
+
+// Sections added after the first version default to empty, so older archives
+// still restore.
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub user: String,
+    pub created_at: DateTime<Utc>,
+    pub vocabulary: Vec<BackupWord>,
+    pub texts: Vec<BackupText>,
+    #[serde(default)]
+    pub profiles: Vec<BackupProfile>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    #[serde(default)]
+    pub lists: Vec<BackupList>,
+    #[serde(default)]
+    pub subscriptions: Vec<BackupSubscription>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupWord {
+    pub text: String,
+    pub language: String,
+    pub status: String,
+    #[serde(default)]
+    pub added_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupText {
+    pub language: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    pub content: String,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupProfile {
+    pub language: String,
+    pub native_language: Option<String>,
+    pub daily_goal: i32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupList {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: bool,
+    pub words: Vec<BackupListWord>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BackupListWord {
+    pub text: String,
+    pub language: String,
+    pub added_at: DateTime<Utc>,
+}
+
+// Subscriptions name the list by its owner, since ids differ between
+// instances.
+#[derive(Serialize, Deserialize)]
+pub struct BackupSubscription {
+    pub owner: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct RestoreReport {
+    pub words: u64,
+    pub texts: u64,
+    pub profiles: u64,
+    pub annotations: u64,
+    pub lists: u64,
+    pub subscriptions: u64,
+    pub skipped: u64,
+    pub skipped_entries: Vec<String>,
+}
+
+impl RestoreReport {
+    fn skip(&mut self, entry: String) {
+        self.skipped += 1;
+        self.skipped_entries.push(entry);
+    }
+}
+
+pub fn create_backup(client: &mut impl GenericClient, id_user: i32) -> Result<Backup, Error> {
+    let user = users::get_user_by_id(client, id_user)?.unwrap_or_default();
+
+    let vocabulary = vocab_user::get_vocabulary_for_user(client, id_user, None)?
+        .into_iter()
+        .map(|entry| BackupWord {
+            text: entry.word,
+            language: entry.language,
+            status: "known".to_string(),
+            added_at: entry.created_at,
+        })
+        .collect();
+
+    let texts = texts::get_texts_for_user(client, id_user, None)?
+        .into_iter()
+        .map(|text| BackupText {
+            language: text.language,
+            title: text.title,
+            content: text.content,
+        })
+        .collect();
+
+    let profiles = profiles::get_profiles_for_user(client, id_user)?
+        .into_iter()
+        .map(|profile| BackupProfile {
+            language: profile.language,
+            native_language: profile.native_language,
+            daily_goal: profile.daily_goal,
+        })
+        .collect();
+
+    let annotations = annotations::search_annotations(client, id_user, None, None, None)?;
+
+    let mut lists = Vec::new();
+    for list in word_lists::get_lists_for_user(client, id_user)? {
+        let words = word_lists::get_list_words(client, id_user, list.id)?
+            .into_iter()
+            .map(|entry| BackupListWord {
+                text: entry.word,
+                language: entry.language,
+                added_at: entry.added_at,
+            })
+            .collect();
+        lists.push(BackupList {
+            name: list.name,
+            description: list.description,
+            is_public: list.is_public,
+            words,
+        });
+    }
+
+    let subscriptions = word_lists::get_subscribed_lists(client, id_user)?
+        .into_iter()
+        .map(|list| BackupSubscription {
+            owner: list.owner,
+            name: list.name,
+        })
+        .collect();
+
+    Ok(Backup {
+        version: BACKUP_VERSION,
+        user,
+        created_at: Utc::now(),
+        vocabulary,
+        texts,
+        profiles,
+        annotations,
+        lists,
+        subscriptions,
+    })
+}
+
+// Inserts the words that are missing and returns the id of each one, in order.
+fn word_ids(client: &mut impl GenericClient, language: &str, texts: &[String]) -> Result<Vec<Option<i32>>, Error> {
+    words::insert_multiple_words(client, texts, language)?;
+    words::get_id_words(client, texts, language)
+}
+
+// Restoring only adds: data the user already has is kept as it is. Entries
+// that cannot be restored are listed in the report.
+pub fn restore_backup(client: &mut impl GenericClient, id_user: i32, backup: &Backup) -> Result<RestoreReport, Error> {
+    let mut report = RestoreReport::default();
+    let mut transaction = client.transaction()?;
+
+    let mut codes: HashMap<&str, Option<String>> = HashMap::new();
+    let names = backup.vocabulary.iter().map(|word| word.language.as_str())
+        .chain(backup.texts.iter().map(|text| text.language.as_str()))
+        .chain(backup.profiles.iter().flat_map(|profile| [Some(profile.language.as_str()), profile.native_language.as_deref()]).flatten())
+        .chain(backup.annotations.iter().map(|annotation| annotation.language.as_str()))
+        .chain(backup.lists.iter().flat_map(|list| list.words.iter().map(|word| word.language.as_str())));
+    for name in names {
+        if !codes.contains_key(name) {
+            codes.insert(name, languages::find_language_code(&mut transaction, name)?);
+        }
+    }
+
+    let mut by_language: BTreeMap<&str, Vec<&BackupWord>> = BTreeMap::new();
+    for word in &backup.vocabulary {
+        if word.status != "known" {
+            report.skip(format!("word '{}' ({}): unsupported status '{}'", word.text, word.language, word.status));
+            continue;
+        }
+        match &codes[word.language.as_str()] {
+            Some(code) => by_language.entry(code.as_str()).or_default().push(word),
+            None => report.skip(format!("word '{}': unknown language '{}'", word.text, word.language)),
+        }
+    }
+
+    for (language, entries) in by_language {
+        let texts: Vec<String> = entries.iter().map(|word| word.text.clone()).collect();
+        let ids = word_ids(&mut transaction, language, &texts)?;
+
+        let dated: Vec<(i32, Option<DateTime<Utc>>)> = ids
+            .into_iter()
+            .zip(&entries)
+            .filter_map(|(id, word)| Some((id?, word.added_at)))
+            .collect();
+
+        report.words += vocab_user::insert_vocab_users_with_dates(&mut transaction, id_user, &dated)?;
+    }
+
+    let existing: HashSet<(String, String)> = texts::get_texts_for_user(&mut transaction, id_user, None)?
+        .into_iter()
+        .map(|text| (text.language, text.content))
+        .collect();
+
+    for text in &backup.texts {
+        let Some(language) = &codes[text.language.as_str()] else {
+            report.skip(format!("text '{}': unknown language '{}'", text.title.as_deref().unwrap_or_default(), text.language));
+            continue;
+        };
+        if existing.contains(&(language.clone(), text.content.clone())) {
+            continue;
+        }
+        texts::insert_text(&mut transaction, id_user, language, text.title.as_deref(), &text.content)?;
+        report.texts += 1;
+    }
+
+    for profile in &backup.profiles {
+        let Some(language) = &codes[profile.language.as_str()] else {
+            report.skip(format!("profile: unknown language '{}'", profile.language));
+            continue;
+        };
+        let native_language = match profile.native_language.as_deref() {
+            Some(name) => match &codes[name] {
+                Some(code) => Some(code.as_str()),
+                None => {
+                    report.skip(format!("profile '{}': unknown native language '{}'", profile.language, name));
+                    continue;
+                }
+            },
+            None => None,
+        };
+        report.profiles += profiles::insert_profile(&mut transaction, id_user, language, native_language, profile.daily_goal)?;
+    }
+
+    for annotation in &backup.annotations {
+        let Some(language) = &codes[annotation.language.as_str()] else {
+            report.skip(format!("annotation '{}': unknown language '{}'", annotation.word, annotation.language));
+            continue;
+        };
+        let Some(id_word) = word_ids(&mut transaction, language, std::slice::from_ref(&annotation.word))?[0] else {
+            report.skip(format!("annotation '{}': invalid word", annotation.word));
+            continue;
+        };
+        report.annotations += annotations::insert_annotation(&mut transaction, id_user, id_word, annotation)?;
+    }
+
+    for list in &backup.lists {
+        // A list with the same name gains the words it is missing.
+        let id_list = match word_lists::find_list_id(&mut transaction, id_user, &list.name)? {
+            Some(id_list) => id_list,
+            None => match word_lists::create_list(&mut transaction, id_user, &list.name, list.description.as_deref(), list.is_public) {
+                Ok(created) => {
+                    report.lists += 1;
+                    created.id
+                }
+                Err(UpdateError::Db(err)) => return Err(err),
+                Err(_) => {
+                    report.skip(format!("list '{}': cannot be created", list.name));
+                    continue;
+                }
+            },
+        };
+
+        let mut by_language: BTreeMap<&str, Vec<&BackupListWord>> = BTreeMap::new();
+        for word in &list.words {
+            match &codes[word.language.as_str()] {
+                Some(code) => by_language.entry(code.as_str()).or_default().push(word),
+                None => report.skip(format!("list '{}', word '{}': unknown language '{}'", list.name, word.text, word.language)),
+            }
+        }
+        for (language, entries) in by_language {
+            let texts: Vec<String> = entries.iter().map(|word| word.text.clone()).collect();
+            let dated: Vec<(i32, DateTime<Utc>)> = word_ids(&mut transaction, language, &texts)?
+                .into_iter()
+                .zip(&entries)
+                .filter_map(|(id, word)| Some((id?, word.added_at)))
+                .collect();
+            word_lists::add_words_with_dates(&mut transaction, id_list, &dated)?;
+        }
+    }
+
+    for subscription in &backup.subscriptions {
+        // Subscriptions to the user's own lists follow the account, whatever
+        // its name on this instance.
+        let owner = if subscription.owner == backup.user {
+            Some(id_user)
+        } else {
+            users::get_user_by_name(&mut transaction, &subscription.owner)?
+        };
+        let id_list = match owner {
+            Some(owner) => word_lists::find_list_id(&mut transaction, owner, &subscription.name)?,
+            None => None,
+        };
+        let visible = match id_list {
+            Some(id_list) => word_lists::get_visible_list(&mut transaction, id_user, id_list)?.map(|list| list.id),
+            None => None,
+        };
+        let Some(id_list) = visible else {
+            report.skip(format!("subscription to '{}' by {}: list not found", subscription.name, subscription.owner));
+            continue;
+        };
+        report.subscriptions += word_lists::subscribe(&mut transaction, id_user, id_list)?;
+    }
+
+    transaction.commit()?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::annotations::upsert_annotation;
+    use crate::db::profiles::upsert_profile;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::insert_vocab_user;
+    use crate::db::words::insert_word;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_backup_and_restore() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let source_id = insert_user(&mut transaction, "source").unwrap();
+        let word_id = insert_word(&mut transaction, "cat", "en").unwrap();
+        insert_vocab_user(&mut transaction, source_id, word_id).unwrap();
+        texts::insert_text(&mut transaction, source_id, "en", Some("Chapter 1"), "The cat sat.").unwrap();
+
+        let backup = create_backup(&mut transaction, source_id).unwrap();
+        assert_eq!(backup.user, "source");
+        assert_eq!(backup.vocabulary.len(), 1);
+        assert_eq!(backup.texts.len(), 1);
+
+        let json = serde_json::to_string(&backup).unwrap();
+        let mut backup: Backup = serde_json::from_str(&json).unwrap();
+        backup.vocabulary.push(BackupWord {
+            text: "hund".to_string(),
+            language: "Deutsch".to_string(),
+            status: "known".to_string(),
+            added_at: Some(backup.created_at),
+        });
+        backup.vocabulary.push(BackupWord {
+            text: "qapla".to_string(),
+            language: "klingon".to_string(),
+            status: "known".to_string(),
+            added_at: Some(backup.created_at),
+        });
+        backup.vocabulary.push(BackupWord {
+            text: "dog".to_string(),
+            language: "en".to_string(),
+            status: "learning".to_string(),
+            added_at: Some(backup.created_at),
+        });
+
+        let target_id = insert_user(&mut transaction, "target").unwrap();
+        let report = restore_backup(&mut transaction, target_id, &backup).unwrap();
+        assert_eq!((report.words, report.texts, report.skipped), (2, 1, 2));
+        assert_eq!(report.skipped_entries, vec![
+            "word 'qapla': unknown language 'klingon'",
+            "word 'dog' (en): unsupported status 'learning'",
+        ]);
+
+        let mut known = vocab_user::get_words_for_user(&mut transaction, target_id).unwrap();
+        known.sort();
+        let hund_id = words::get_id_word(&mut transaction, "hund", "de").unwrap().unwrap();
+        let mut expected = vec![word_id, hund_id];
+        expected.sort();
+        assert_eq!(known, expected);
+
+        let report = restore_backup(&mut transaction, target_id, &backup).unwrap();
+        assert_eq!((report.words, report.texts, report.skipped), (0, 0, 2));
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_backup_keeps_profiles_annotations_and_lists() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let source_id = insert_user(&mut transaction, "source").unwrap();
+        let teacher_id = insert_user(&mut transaction, "unitbackupteacher").unwrap();
+        let cat_id = insert_word(&mut transaction, "cat", "en").unwrap();
+        upsert_profile(&mut transaction, source_id, "en", Some("pt"), 10).unwrap();
+        upsert_annotation(&mut transaction, source_id, cat_id, Some("gato"), None, &["animals".to_string()]).unwrap();
+        let own = word_lists::create_list(&mut transaction, source_id, "animals", Some("pets"), false).unwrap();
+        word_lists::add_words(&mut transaction, own.id, &[cat_id]).unwrap();
+        let shared = word_lists::create_list(&mut transaction, teacher_id, "unit A1", None, true).unwrap();
+        word_lists::subscribe(&mut transaction, source_id, shared.id).unwrap();
+
+        let json = serde_json::to_string(&create_backup(&mut transaction, source_id).unwrap()).unwrap();
+        let mut backup: Backup = serde_json::from_str(&json).unwrap();
+        backup.subscriptions.push(BackupSubscription {
+            owner: "nobody".to_string(),
+            name: "missing".to_string(),
+        });
+
+        let target_id = insert_user(&mut transaction, "target").unwrap();
+        let report = restore_backup(&mut transaction, target_id, &backup).unwrap();
+        assert_eq!((report.profiles, report.annotations, report.lists, report.subscriptions), (1, 1, 1, 1));
+        assert_eq!(report.skipped_entries, vec!["subscription to 'missing' by nobody: list not found"]);
+
+        let profile = profiles::get_profile(&mut transaction, target_id, "en").unwrap().unwrap();
+        assert_eq!((profile.native_language.as_deref(), profile.daily_goal), (Some("pt"), 10));
+        let annotation = annotations::get_annotation(&mut transaction, target_id, cat_id).unwrap().unwrap();
+        assert_eq!(annotation.translation.as_deref(), Some("gato"));
+        let lists = word_lists::get_lists_for_user(&mut transaction, target_id).unwrap();
+        assert_eq!((lists[0].name.as_str(), lists[0].description.as_deref(), lists[0].total_words), ("animals", Some("pets"), 1));
+        assert_eq!(word_lists::get_subscribed_lists(&mut transaction, target_id).unwrap()[0].id, shared.id);
+
+        let report = restore_backup(&mut transaction, target_id, &backup).unwrap();
+        assert_eq!((report.profiles, report.annotations, report.lists, report.subscriptions), (0, 0, 0, 0));
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_restore_first_version() {
+        let backup: Backup = serde_json::from_str(
+            r#"{"version": 1, "user": "old", "created_at": "2024-01-01T00:00:00Z", "vocabulary": [], "texts": []}"#
+        ).unwrap();
+        assert!(backup.profiles.is_empty() && backup.lists.is_empty());
+    }
+}
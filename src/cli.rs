@@ -0,0 +1,273 @@
+use clap::{Parser, Subcommand};
+use postgres::{Client, NoTls};
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process;
+use token_analyser::backup::{self, Backup, BACKUP_VERSION};
+use token_analyser::db::{dictionaries, languages, users, word_lists};
+use token_analyser::dictionary::{self, DictionaryFormat};
+use token_analyser::export::{self, ExportFormat};
+use token_analyser::import::{self, ImportFormat};
+
+#[derive(Parser)]
+#[command(about = "Vocabulary analyser server and tools")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Export a user's vocabulary as CSV, JSON or an Anki deck
+    Export {
+        /// Name of the user whose vocabulary is exported
+        #[arg(long)]
+        user: String,
+        /// Only export words in this language (ISO 639 code, e.g. "en")
+        #[arg(long)]
+        language: Option<String>,
+        /// Only export words with this status ("known" or "unknown")
+        #[arg(long)]
+        status: Option<String>,
+        /// Only export words in this word list
+        #[arg(long)]
+        list: Option<String>,
+        /// Output format: csv, json or anki
+        #[arg(long, default_value = "csv")]
+        format: String,
+        /// File to write to; defaults to standard output
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Import a word list and mark its words as known
+    Import {
+        /// Name of the user who receives the words
+        #[arg(long)]
+        user: String,
+        /// ISO 639 code of the imported words, e.g. "en"
+        #[arg(long)]
+        language: String,
+        /// Input format: csv, tsv, anki or lingq
+        #[arg(long, default_value = "csv")]
+        format: String,
+        /// File to read the word list from
+        #[arg(long)]
+        file: PathBuf,
+        /// Only report what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write a full account backup as a JSON archive
+    Backup {
+        /// Name of the user to back up
+        #[arg(long)]
+        user: String,
+        /// File to write to; defaults to standard output
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Restore a JSON archive written by `backup` into an account
+    Restore {
+        /// Name of the user who receives the data
+        #[arg(long)]
+        user: String,
+        /// Archive to restore
+        #[arg(long)]
+        file: PathBuf,
+    },
+    /// Import a StarDict dictionary or a Wiktionary JSON Lines extract
+    ImportDictionary {
+        /// Name shown for the dictionary; importing the same name again replaces it
+        #[arg(long)]
+        name: String,
+        /// Input format: stardict or wiktionary
+        #[arg(long)]
+        format: String,
+        /// ISO 639 code of the headwords, e.g. "de"
+        #[arg(long)]
+        source: String,
+        /// ISO 639 code of the definitions, e.g. "en"
+        #[arg(long)]
+        target: String,
+        /// Dictionary files (.ifo, .idx and .dict or .dict.dz for StarDict)
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
+
+pub fn run(command: Command) {
+    match command {
+        Command::Export { user, language, status, list, format, output } => {
+            export_vocabulary(&user, language.as_deref(), status.as_deref(), list.as_deref(), &format, output)
+        }
+        Command::Import { user, language, format, file, dry_run } => {
+            import_vocabulary(&user, &language, &format, file, dry_run)
+        }
+        Command::Backup { user, output } => backup_account(&user, output),
+        Command::Restore { user, file } => restore_account(&user, file),
+        Command::ImportDictionary { name, format, source, target, files } => {
+            import_dictionary(&name, &format, &source, &target, files)
+        }
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn connect() -> Client {
+    let db_url = env::var("DATABASE_URL").unwrap();
+    Client::connect(&db_url, NoTls).unwrap()
+}
+
+fn find_user(client: &mut Client, name: &str) -> i32 {
+    match users::get_user_by_name(client, name).unwrap() {
+        Some(user_id) => user_id,
+        None => fail(&format!("User '{}' not found", name)),
+    }
+}
+
+fn check_language(client: &mut Client, code: &str) {
+    if languages::get_language(client, code).unwrap().is_none() {
+        fail(&format!("Unknown language code: {}", code));
+    }
+}
+
+fn export_vocabulary(user: &str, language: Option<&str>, status: Option<&str>, list: Option<&str>, format: &str, output: Option<PathBuf>) {
+    let Some(format) = ExportFormat::parse(format) else {
+        fail(&format!("Invalid format: {}", format));
+    };
+
+    if status.is_some_and(|status| status != "known" && status != "unknown") {
+        fail(&format!("Invalid status: {}", status.unwrap()));
+    }
+
+    let mut client = connect();
+
+    let user_id = find_user(&mut client, user);
+    if let Some(language) = language {
+        check_language(&mut client, language);
+    }
+
+    let list_id = list.map(|name| match word_lists::find_list_id(&mut client, user_id, name).unwrap() {
+        Some(list_id) => list_id,
+        None => fail(&format!("Word list '{}' not found", name)),
+    });
+
+    let entries = export::collect_entries(&mut client, user_id, language, status, list_id).unwrap();
+    let rendered = export::render(&entries, format);
+
+    match output {
+        Some(path) => fs::write(&path, rendered).unwrap(),
+        None => print!("{}", rendered),
+    }
+}
+
+fn import_vocabulary(user: &str, language: &str, format: &str, file: PathBuf, dry_run: bool) {
+    let Some(format) = ImportFormat::parse(format) else {
+        fail(&format!("Invalid format: {}", format));
+    };
+
+    let content = fs::read_to_string(&file).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", file.display(), err)));
+
+    let mut client = connect();
+
+    let user_id = find_user(&mut client, user);
+    check_language(&mut client, language);
+
+    let entries = import::parse_entries(&content, format);
+    let report = import::import_words(&mut client, user_id, language, &entries, dry_run).unwrap();
+
+    println!("new: {}", report.new.len());
+    println!("existing: {}", report.existing.len());
+    println!("rejected: {}", report.rejected.len());
+    for entry in &report.rejected {
+        println!("  {}", entry);
+    }
+}
+
+fn backup_account(user: &str, output: Option<PathBuf>) {
+    let mut client = connect();
+    let user_id = find_user(&mut client, user);
+
+    let backup = backup::create_backup(&mut client, user_id).unwrap();
+    let archive = serde_json::to_string_pretty(&backup).unwrap();
+
+    match output {
+        Some(path) => fs::write(&path, archive).unwrap(),
+        None => println!("{}", archive),
+    }
+}
+
+fn restore_account(user: &str, file: PathBuf) {
+    let content = fs::read_to_string(&file).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", file.display(), err)));
+    let backup: Backup = serde_json::from_str(&content).unwrap_or_else(|err| fail(&format!("Invalid backup archive: {}", err)));
+
+    if backup.version > BACKUP_VERSION {
+        fail(&format!("Unsupported backup version: {}", backup.version));
+    }
+
+    let mut client = connect();
+    let user_id = find_user(&mut client, user);
+
+    let report = backup::restore_backup(&mut client, user_id, &backup).unwrap();
+
+    println!("words: {}", report.words);
+    println!("texts: {}", report.texts);
+    println!("profiles: {}", report.profiles);
+    println!("annotations: {}", report.annotations);
+    println!("lists: {}", report.lists);
+    println!("subscriptions: {}", report.subscriptions);
+    println!("skipped: {}", report.skipped);
+    for entry in &report.skipped_entries {
+        println!("  {}", entry);
+    }
+}
+
+fn import_dictionary(name: &str, format: &str, source: &str, target: &str, files: Vec<PathBuf>) {
+    let Some(format) = DictionaryFormat::parse(format) else {
+        fail(&format!("Invalid format: {}", format));
+    };
+
+    let mut client = connect();
+    check_language(&mut client, source);
+    check_language(&mut client, target);
+
+    let entries = match format {
+        // Wiktionary dumps can be several gigabytes, so they are read line by
+        // line instead of loaded whole; only the entries of the source
+        // language are kept in memory.
+        DictionaryFormat::Wiktionary => {
+            let mut entries = Vec::new();
+            for path in &files {
+                let file = File::open(path).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", path.display(), err)));
+                let reader: Box<dyn BufRead> = if path.extension().is_some_and(|extension| extension == "gz") {
+                    Box::new(BufReader::new(flate2::read::GzDecoder::new(file)))
+                } else {
+                    Box::new(BufReader::new(file))
+                };
+                let parsed = dictionary::parse_wiktionary(reader, source)
+                    .unwrap_or_else(|err| fail(&format!("{}: {}", path.display(), err)));
+                entries.extend(parsed);
+            }
+            entries
+        }
+        DictionaryFormat::StarDict => {
+            let files: Vec<(String, Vec<u8>)> = files
+                .iter()
+                .map(|path| {
+                    let bytes = fs::read(path).unwrap_or_else(|err| fail(&format!("Cannot read {}: {}", path.display(), err)));
+                    (path.to_string_lossy().into_owned(), bytes)
+                })
+                .collect();
+            dictionary::parse_files(format, &files, source).unwrap_or_else(|err| fail(&err.to_string()))
+        }
+    };
+
+    let imported = dictionaries::import_dictionary(&mut client, name, source, target, format.name(), &entries).unwrap();
+
+    println!("entries: {}", imported.entries);
+}
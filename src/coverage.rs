@@ -0,0 +1,257 @@
+use postgres::{GenericClient, Error};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use crate::db::{groups, vocab_user};
+use crate::db::texts::Text;
+use crate::tokenizer::frequency_counter_from_text;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Coverage {
+    pub total_tokens: u32,
+    pub unique_words: usize,
+    pub known_tokens: u32,
+    pub known_words: usize,
+    pub unknown_words: Vec<String>,
+    pub coverage: f64,
+}
+
+pub fn compute_coverage(client: &mut impl GenericClient, id_user: i32, language: &str, text: &str) -> Result<Coverage, Error> {
+    let freq = frequency_counter_from_text(text);
+    let words: Vec<String> = freq.iter().map(|(word, _)| word.clone()).collect();
+    let known = vocab_user::get_known_words_among(client, id_user, language, &words)?;
+    Ok(coverage_from(freq, &known))
+}
+
+fn coverage_from(freq: Vec<(String, u32)>, known: &HashSet<String>) -> Coverage {
+    let unique_words = freq.len();
+    let mut total_tokens = 0;
+    let mut known_tokens = 0;
+    let mut unknown_words = Vec::new();
+    for (word, count) in freq {
+        total_tokens += count;
+        if known.contains(&word) {
+            known_tokens += count;
+        } else {
+            unknown_words.push(word);
+        }
+    }
+
+    let coverage = if total_tokens == 0 {
+        0.0
+    } else {
+        (known_tokens as f64 * 1000.0 / total_tokens as f64).round() / 10.0
+    };
+
+    Coverage {
+        total_tokens,
+        unique_words,
+        known_tokens,
+        known_words: unique_words - unknown_words.len(),
+        unknown_words,
+        coverage,
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TextDifficulty {
+    pub id: i32,
+    pub title: Option<String>,
+    pub language: String,
+    pub total_tokens: u32,
+    pub unknown_tokens: u32,
+    pub unknown_words: usize,
+    pub unknown_percent: f64,
+}
+
+// Ranks texts from the easiest to the hardest for the user, by the share of
+// tokens they do not know yet. The known words are looked up once per
+// language, for the distinct words of all texts together. Texts without any
+// word are left out.
+pub fn rank_texts(client: &mut impl GenericClient, id_user: i32, texts: &[Text]) -> Result<Vec<TextDifficulty>, Error> {
+    let frequencies: Vec<Vec<(String, u32)>> = texts
+        .iter()
+        .map(|text| frequency_counter_from_text(&text.content))
+        .collect();
+
+    let mut words_by_language: HashMap<&str, HashSet<String>> = HashMap::new();
+    for (text, freq) in texts.iter().zip(&frequencies) {
+        words_by_language
+            .entry(text.language.as_str())
+            .or_default()
+            .extend(freq.iter().map(|(word, _)| word.clone()));
+    }
+
+    let mut known_by_language = HashMap::new();
+    for (language, words) in words_by_language {
+        let words: Vec<String> = words.into_iter().collect();
+        let known = vocab_user::get_known_words_among(client, id_user, language, &words)?;
+        known_by_language.insert(language, known);
+    }
+
+    let mut ranking = Vec::new();
+    for (text, freq) in texts.iter().zip(frequencies) {
+        let coverage = coverage_from(freq, &known_by_language[text.language.as_str()]);
+        if coverage.total_tokens == 0 {
+            continue;
+        }
+
+        let unknown_tokens = coverage.total_tokens - coverage.known_tokens;
+        ranking.push(TextDifficulty {
+            id: text.id,
+            title: text.title.clone(),
+            language: text.language.clone(),
+            total_tokens: coverage.total_tokens,
+            unknown_tokens,
+            unknown_words: coverage.unknown_words.len(),
+            unknown_percent: (unknown_tokens as f64 * 1000.0 / coverage.total_tokens as f64).round() / 10.0,
+        });
+    }
+
+    ranking.sort_by(|a, b| {
+        a.unknown_percent.total_cmp(&b.unknown_percent)
+            .then(a.unknown_words.cmp(&b.unknown_words))
+            .then(a.id.cmp(&b.id))
+    });
+    Ok(ranking)
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ClassWord {
+    pub word: String,
+    pub occurrences: u32,
+    pub unknown_to: i64,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ClassReport {
+    pub students: i64,
+    pub unique_words: usize,
+    pub words: Vec<ClassWord>,
+}
+
+// Words of the text that more than half of the group's students do not know,
+// the least known and most frequent first.
+pub fn compute_class_report(client: &mut impl GenericClient, id_group: i32, language: &str, text: &str) -> Result<ClassReport, Error> {
+    let students = groups::get_members(client, id_group)?
+        .iter()
+        .filter(|member| member.role == "student" && !member.pending)
+        .count() as i64;
+
+    let freq = frequency_counter_from_text(text);
+    let words: Vec<String> = freq.iter().map(|(word, _)| word.clone()).collect();
+    let knowing = groups::count_students_knowing(client, id_group, language, &words)?;
+
+    let mut report: Vec<ClassWord> = freq
+        .into_iter()
+        .map(|(word, occurrences)| {
+            let unknown_to = students - knowing.get(&word).copied().unwrap_or(0);
+            ClassWord { word, occurrences, unknown_to }
+        })
+        .filter(|word| word.unknown_to * 2 > students)
+        .collect();
+    report.sort_by(|a, b| {
+        b.unknown_to.cmp(&a.unknown_to)
+            .then(b.occurrences.cmp(&a.occurrences))
+            .then(a.word.cmp(&b.word))
+    });
+
+    Ok(ClassReport {
+        students,
+        unique_words: words.len(),
+        words: report,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::groups::{accept_invitation, add_member, create_group};
+    use crate::db::texts::{get_texts_for_user, insert_text};
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::insert_vocab_user;
+    use crate::db::words::insert_word;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_compute_coverage() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let the_id = insert_word(&mut client, "the", "en").unwrap();
+        insert_vocab_user(&mut client, user_id, the_id).unwrap();
+
+        let coverage = compute_coverage(&mut client, user_id, "en", "The cat saw the dog.").unwrap();
+        assert_eq!(coverage.total_tokens, 5);
+        assert_eq!(coverage.unique_words, 4);
+        assert_eq!(coverage.known_tokens, 2);
+        assert_eq!(coverage.known_words, 1);
+        assert_eq!(coverage.unknown_words.len(), 3);
+        assert_eq!(coverage.coverage, 40.0);
+
+        let empty = compute_coverage(&mut client, user_id, "en", "").unwrap();
+        assert_eq!(empty.coverage, 0.0);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_compute_class_report() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let teacher_id = insert_user(&mut client, "teacher").unwrap();
+        let group = create_group(&mut client, teacher_id, "English B1").unwrap();
+        let the_id = insert_word(&mut client, "the", "en").unwrap();
+        let cat_id = insert_word(&mut client, "cat", "en").unwrap();
+        for name in ["ana", "bruno", "carla"] {
+            let student_id = insert_user(&mut client, name).unwrap();
+            add_member(&mut client, group.id, student_id, "student").unwrap();
+            accept_invitation(&mut client, group.id, student_id).unwrap();
+            insert_vocab_user(&mut client, student_id, the_id).unwrap();
+            if name == "ana" {
+                insert_vocab_user(&mut client, student_id, cat_id).unwrap();
+            }
+        }
+
+        let report = compute_class_report(&mut client, group.id, "en", "The cat saw the dog. The dog ran.").unwrap();
+        assert_eq!(report.students, 3);
+        assert_eq!(report.unique_words, 5);
+        let words: Vec<(&str, u32, i64)> = report.words.iter().map(|word| (word.word.as_str(), word.occurrences, word.unknown_to)).collect();
+        assert_eq!(words, vec![("dog", 2, 3), ("ran", 1, 3), ("saw", 1, 3), ("cat", 1, 2)]);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_rank_texts() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        for word in ["the", "cat", "sat"] {
+            let word_id = insert_word(&mut client, word, "en").unwrap();
+            insert_vocab_user(&mut client, user_id, word_id).unwrap();
+        }
+        let hard_id = insert_text(&mut client, user_id, "en", Some("hard"), "The ocelot prowled stealthily.").unwrap();
+        let easy_id = insert_text(&mut client, user_id, "en", Some("easy"), "The cat sat. The cat ran.").unwrap();
+        insert_text(&mut client, user_id, "en", Some("empty"), "...").unwrap();
+        let portuguese_id = insert_text(&mut client, user_id, "pt", Some("pt"), "The cat.").unwrap();
+
+        let texts = get_texts_for_user(&mut client, user_id, None).unwrap();
+        let ranking = rank_texts(&mut client, user_id, &texts).unwrap();
+        assert_eq!(ranking.iter().map(|text| text.id).collect::<Vec<_>>(), vec![easy_id, hard_id, portuguese_id]);
+        assert_eq!((ranking[0].total_tokens, ranking[0].unknown_tokens, ranking[0].unknown_words), (6, 1, 1));
+        assert_eq!(ranking[0].unknown_percent, 16.7);
+        assert_eq!(ranking[1].unknown_percent, 75.0);
+        assert_eq!(ranking[2].unknown_percent, 100.0);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Annotation {
+    pub word: String,
+    pub language: String,
+    pub translation: Option<String>,
+    pub note: Option<String>,
+    pub tags: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+const ANNOTATION_COLUMNS: &str = "w.text, w.language, a.translation, a.note, a.tags, a.created_at, a.updated_at";
+
+fn annotation_from_row(row: &Row) -> Annotation {
+    Annotation {
+        word: row.get("text"),
+        language: row.get("language"),
+        translation: row.get("translation"),
+        note: row.get("note"),
+        tags: row.get("tags"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut normalized: Vec<String> = tags
+        .iter()
+        .map(|tag| tag.trim().to_lowercase())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    normalized.sort();
+    normalized.dedup();
+    normalized
+}
+
+pub fn upsert_annotation(client: &mut impl GenericClient, id_user: i32, id_word: i32, translation: Option<&str>, note: Option<&str>, tags: &[String]) -> Result<Annotation, Error> {
+    let tags = normalize_tags(tags);
+    let row = client.query_one(
+        &format!(
+            "WITH saved AS (
+                 INSERT INTO word_annotations (id_user, id_word, translation, note, tags)
+                 VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (id_user, id_word) DO UPDATE
+                 SET translation = EXCLUDED.translation, note = EXCLUDED.note, tags = EXCLUDED.tags, updated_at = now()
+                 RETURNING *
+             )
+             SELECT {} FROM saved a JOIN words w ON w.id = a.id_word",
+            ANNOTATION_COLUMNS
+        ),
+        &[&id_user, &id_word, &translation, &note, &tags]
+    )?;
+    Ok(annotation_from_row(&row))
+}
+
+// Restores an annotation with its original dates, keeping the user's current
+// annotation of the word when there is one.
+pub fn insert_annotation(client: &mut impl GenericClient, id_user: i32, id_word: i32, annotation: &Annotation) -> Result<u64, Error> {
+    let tags = normalize_tags(&annotation.tags);
+    let count = client.execute(
+        "INSERT INTO word_annotations (id_user, id_word, translation, note, tags, created_at, updated_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         ON CONFLICT (id_user, id_word) DO NOTHING",
+        &[&id_user, &id_word, &annotation.translation, &annotation.note, &tags, &annotation.created_at, &annotation.updated_at]
+    )?;
+    Ok(count)
+}
+
+pub fn get_annotation(client: &mut impl GenericClient, id_user: i32, id_word: i32) -> Result<Option<Annotation>, Error> {
+    let row = client.query_opt(
+        &format!(
+            "SELECT {} FROM word_annotations a JOIN words w ON w.id = a.id_word
+             WHERE a.id_user = $1 AND a.id_word = $2",
+            ANNOTATION_COLUMNS
+        ),
+        &[&id_user, &id_word]
+    )?;
+    Ok(row.as_ref().map(annotation_from_row))
+}
+
+pub fn get_annotations_among(client: &mut impl GenericClient, id_user: i32, language: &str, words: &[String]) -> Result<HashMap<String, Annotation>, Error> {
+    if words.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = client.query(
+        &format!(
+            "SELECT {} FROM word_annotations a JOIN words w ON w.id = a.id_word
+             WHERE a.id_user = $1 AND w.language = $2 AND w.text = ANY($3)",
+            ANNOTATION_COLUMNS
+        ),
+        &[&id_user, &language, &words]
+    )?;
+    Ok(rows.iter().map(annotation_from_row).map(|annotation| (annotation.word.clone(), annotation)).collect())
+}
+
+// Without a query every annotation matching the filters is listed, most
+// recently edited first; with one, the best matches come first.
+pub fn search_annotations(client: &mut impl GenericClient, id_user: i32, query: Option<&str>, tag: Option<&str>, language: Option<&str>) -> Result<Vec<Annotation>, Error> {
+    let tag = tag.map(|tag| tag.trim().to_lowercase());
+    let rows = client.query(
+        &format!(
+            "SELECT {} FROM word_annotations a JOIN words w ON w.id = a.id_word
+             WHERE a.id_user = $1
+               AND ($2::TEXT IS NULL OR a.search @@ websearch_to_tsquery('simple', $2))
+               AND ($3::TEXT IS NULL OR $3 = ANY(a.tags))
+               AND ($4::TEXT IS NULL OR w.language = $4)
+             ORDER BY ts_rank(a.search, websearch_to_tsquery('simple', coalesce($2, ''))) DESC, a.updated_at DESC",
+            ANNOTATION_COLUMNS
+        ),
+        &[&id_user, &query, &tag, &language]
+    )?;
+    Ok(rows.iter().map(annotation_from_row).collect())
+}
+
+pub fn delete_annotation(client: &mut impl GenericClient, id_user: i32, id_word: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM word_annotations WHERE id_user = $1 AND id_word = $2",
+        &[&id_user, &id_word]
+    )?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::words::{insert_word, merge_words};
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn test_upsert_annotation() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let word_id = insert_word(&mut client, "unitnote", "de").unwrap();
+
+        let annotation = upsert_annotation(&mut client, user_id, word_id, Some("note"), None, &tags(&["Nouns", " nouns", ""])).unwrap();
+        assert_eq!(annotation.word, "unitnote");
+        assert_eq!(annotation.tags, vec!["nouns"]);
+
+        let updated = upsert_annotation(&mut client, user_id, word_id, None, Some("seen in chapter 3"), &[]).unwrap();
+        assert_eq!(updated.translation, None);
+        assert_eq!(updated.note.as_deref(), Some("seen in chapter 3"));
+        assert_eq!(updated.created_at, annotation.created_at);
+
+        let found = get_annotations_among(&mut client, user_id, "de", &tags(&["unitnote", "other"])).unwrap();
+        assert_eq!(found.len(), 1);
+
+        assert_eq!(delete_annotation(&mut client, user_id, word_id).unwrap(), 1);
+        assert!(get_annotation(&mut client, user_id, word_id).unwrap().is_none());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_search_annotations() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let house_id = insert_word(&mut client, "unithaus", "de").unwrap();
+        let dog_id = insert_word(&mut client, "unithund", "de").unwrap();
+        upsert_annotation(&mut client, user_id, house_id, Some("house"), Some("Neuter noun, plural Häuser"), &tags(&["nouns"])).unwrap();
+        upsert_annotation(&mut client, user_id, dog_id, Some("dog"), Some("masculine noun"), &tags(&["animals"])).unwrap();
+
+        let words = |annotations: Vec<Annotation>| annotations.into_iter().map(|annotation| annotation.word).collect::<Vec<_>>();
+
+        assert_eq!(words(search_annotations(&mut client, user_id, Some("plural"), None, None).unwrap()), vec!["unithaus"]);
+        assert_eq!(words(search_annotations(&mut client, user_id, Some("noun"), None, None).unwrap()).len(), 2);
+        assert_eq!(words(search_annotations(&mut client, user_id, Some("noun -masculine"), None, None).unwrap()), vec!["unithaus"]);
+        assert_eq!(words(search_annotations(&mut client, user_id, None, Some("Animals"), None).unwrap()), vec!["unithund"]);
+        assert!(search_annotations(&mut client, user_id, None, None, Some("en")).unwrap().is_empty());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_merge_keeps_annotation() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let from_id = insert_word(&mut transaction, "unitnotefrom", "de").unwrap();
+        let into_id = insert_word(&mut transaction, "unitnoteinto", "de").unwrap();
+        upsert_annotation(&mut transaction, user_id, from_id, Some("kept"), None, &[]).unwrap();
+
+        merge_words(&mut transaction, from_id, into_id, None).unwrap();
+
+        let annotation = get_annotation(&mut transaction, user_id, into_id).unwrap().unwrap();
+        assert_eq!(annotation.translation.as_deref(), Some("kept"));
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_merge_combines_annotations() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let from_id = insert_word(&mut transaction, "unitnotefrom", "de").unwrap();
+        let into_id = insert_word(&mut transaction, "unitnoteinto", "de").unwrap();
+        upsert_annotation(&mut transaction, user_id, from_id, Some("colour"), Some("British spelling"), &tags(&["spelling", "adjectives"])).unwrap();
+        upsert_annotation(&mut transaction, user_id, into_id, Some("color"), None, &tags(&["adjectives"])).unwrap();
+
+        merge_words(&mut transaction, from_id, into_id, None).unwrap();
+
+        let annotation = get_annotation(&mut transaction, user_id, into_id).unwrap().unwrap();
+        assert_eq!(annotation.translation.as_deref(), Some("color; colour"));
+        assert_eq!(annotation.note.as_deref(), Some("British spelling"));
+        assert_eq!(annotation.tags, vec!["adjectives", "spelling"]);
+
+        transaction.rollback().unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error};
+use serde::Serialize;
+
+#[derive(Serialize)]
+pub struct ApiToken {
+    pub id: i32,
+    pub name: String,
+    pub scope: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn insert_api_token(client: &mut impl GenericClient, id_user: i32, name: &str, token_hash: &str, scope: &str) -> Result<i32, Error> {
+    let row = client.query_one(
+        "INSERT INTO api_tokens (id_user, name, token_hash, scope) VALUES ($1, $2, $3, $4) RETURNING id",
+        &[&id_user, &name, &token_hash, &scope]
+    )?;
+    Ok(row.get("id"))
+}
+
+pub fn get_api_tokens_for_user(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<ApiToken>, Error> {
+    let rows = client.query(
+        "SELECT id, name, scope, created_at FROM api_tokens WHERE id_user = $1 ORDER BY id",
+        &[&id_user]
+    )?;
+    let tokens = rows.iter().map(|row| ApiToken {
+        id: row.get("id"),
+        name: row.get("name"),
+        scope: row.get("scope"),
+        created_at: row.get("created_at"),
+    }).collect();
+    Ok(tokens)
+}
+
+pub fn get_user_for_api_token(client: &mut impl GenericClient, token_hash: &str) -> Result<Option<(i32, String)>, Error> {
+    let row = client.query_opt("SELECT id_user, scope FROM api_tokens WHERE token_hash = $1", &[&token_hash])?;
+    Ok(row.map(|r| (r.get("id_user"), r.get("scope"))))
+}
+
+pub fn delete_api_token(client: &mut impl GenericClient, id_user: i32, id: i32) -> Result<u64, Error> {
+    let count = client.execute("DELETE FROM api_tokens WHERE id = $1 AND id_user = $2", &[&id, &id_user])?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_insert_api_token() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        insert_api_token(&mut client, user_id, "script", "tokenhash", "read").unwrap();
+
+        let fetched = get_user_for_api_token(&mut client, "tokenhash").unwrap();
+        assert_eq!(fetched, Some((user_id, "read".to_string())));
+
+        let invalid_scope = insert_api_token(&mut client, user_id, "bad", "otherhash", "admin");
+        assert!(invalid_scope.is_err());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_get_api_tokens_for_user() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let id1 = insert_api_token(&mut client, user_id, "export", "hash1", "read").unwrap();
+        let id2 = insert_api_token(&mut client, user_id, "import", "hash2", "read_write").unwrap();
+
+        let tokens = get_api_tokens_for_user(&mut client, user_id).unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(tokens[0].id, id1);
+        assert_eq!(tokens[0].name, "export");
+        assert_eq!(tokens[1].id, id2);
+        assert_eq!(tokens[1].scope, "read_write");
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_delete_api_token() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let other_id = insert_user(&mut client, "otheruser").unwrap();
+        let id = insert_api_token(&mut client, user_id, "script", "tokenhash", "read").unwrap();
+
+        let count = delete_api_token(&mut client, other_id, id).unwrap();
+        assert_eq!(count, 0);
+
+        let count = delete_api_token(&mut client, user_id, id).unwrap();
+        assert_eq!(count, 1);
+
+        let fetched = get_user_for_api_token(&mut client, "tokenhash").unwrap();
+        assert_eq!(fetched, None);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
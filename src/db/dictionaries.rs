@@ -0,0 +1,186 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+use crate::dictionary::DictionaryEntry;
+
+const INSERT_CHUNK_SIZE: usize = 5000;
+
+#[derive(Serialize, Debug)]
+pub struct Dictionary {
+    pub id: i32,
+    pub name: String,
+    pub source_language: String,
+    pub target_language: String,
+    pub format: String,
+    pub entries: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Definition {
+    pub dictionary: String,
+    pub target_language: String,
+    pub part_of_speech: Option<String>,
+    pub gloss: String,
+}
+
+fn dictionary_from_row(row: &Row) -> Dictionary {
+    Dictionary {
+        id: row.get("id"),
+        name: row.get("name"),
+        source_language: row.get("source_language"),
+        target_language: row.get("target_language"),
+        format: row.get("format"),
+        entries: row.get("entries"),
+        created_at: row.get("created_at"),
+    }
+}
+
+// Importing a dictionary under an existing name and language pair replaces
+// the previous import.
+pub fn import_dictionary(client: &mut impl GenericClient, name: &str, source_language: &str, target_language: &str, format: &str, entries: &[DictionaryEntry]) -> Result<Dictionary, Error> {
+    let mut transaction = client.transaction()?;
+
+    transaction.execute(
+        "DELETE FROM dictionaries WHERE name = $1 AND source_language = $2 AND target_language = $3",
+        &[&name, &source_language, &target_language]
+    )?;
+
+    let count = entries.len() as i32;
+    let row = transaction.query_one(
+        "INSERT INTO dictionaries (name, source_language, target_language, format, entries)
+         VALUES ($1, $2, $3, $4, $5)
+         RETURNING id, name, source_language, target_language, format, entries, created_at",
+        &[&name, &source_language, &target_language, &format, &count]
+    )?;
+    let dictionary = dictionary_from_row(&row);
+
+    for (chunk_index, chunk) in entries.chunks(INSERT_CHUNK_SIZE).enumerate() {
+        let first = (chunk_index * INSERT_CHUNK_SIZE) as i32;
+        let positions: Vec<i32> = (first..first + chunk.len() as i32).collect();
+        let words: Vec<&str> = chunk.iter().map(|entry| entry.word.as_str()).collect();
+        let parts_of_speech: Vec<Option<&str>> = chunk.iter().map(|entry| entry.part_of_speech.as_deref()).collect();
+        let glosses: Vec<&str> = chunk.iter().map(|entry| entry.gloss.as_str()).collect();
+
+        transaction.execute(
+            "INSERT INTO dictionary_entries (id_dictionary, position, word, part_of_speech, gloss)
+             SELECT $1, * FROM UNNEST($2::int[], $3::text[], $4::text[], $5::text[])",
+            &[&dictionary.id, &positions, &words, &parts_of_speech, &glosses]
+        )?;
+    }
+
+    transaction.commit()?;
+
+    Ok(dictionary)
+}
+
+pub fn get_dictionaries(client: &mut impl GenericClient) -> Result<Vec<Dictionary>, Error> {
+    let rows = client.query(
+        "SELECT id, name, source_language, target_language, format, entries, created_at
+         FROM dictionaries ORDER BY source_language, target_language, name",
+        &[]
+    )?;
+    Ok(rows.iter().map(dictionary_from_row).collect())
+}
+
+pub fn delete_dictionary(client: &mut impl GenericClient, id: i32) -> Result<u64, Error> {
+    let count = client.execute("DELETE FROM dictionaries WHERE id = $1", &[&id])?;
+    Ok(count)
+}
+
+pub fn lookup(client: &mut impl GenericClient, language: &str, target_language: Option<&str>, word: &str) -> Result<Vec<Definition>, Error> {
+    let rows = client.query(
+        "SELECT d.name, d.target_language, e.part_of_speech, e.gloss
+         FROM dictionary_entries e JOIN dictionaries d ON d.id = e.id_dictionary
+         WHERE d.source_language = $1 AND ($2::TEXT IS NULL OR d.target_language = $2) AND e.word = $3
+         ORDER BY d.id, e.position",
+        &[&language, &target_language, &word]
+    )?;
+    let definitions = rows.iter().map(|row| Definition {
+        dictionary: row.get("name"),
+        target_language: row.get("target_language"),
+        part_of_speech: row.get("part_of_speech"),
+        gloss: row.get("gloss"),
+    }).collect();
+    Ok(definitions)
+}
+
+// First definition of each word, preferring dictionaries into
+// `preferred_target` when there are several.
+pub fn get_glosses(client: &mut impl GenericClient, language: &str, preferred_target: Option<&str>, words: &[String]) -> Result<HashMap<String, String>, Error> {
+    if words.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = client.query(
+        "SELECT DISTINCT ON (e.word) e.word, e.gloss
+         FROM dictionary_entries e JOIN dictionaries d ON d.id = e.id_dictionary
+         WHERE d.source_language = $1 AND e.word = ANY($3)
+         ORDER BY e.word, d.target_language IS NOT DISTINCT FROM $2 DESC, d.id, e.position",
+        &[&language, &preferred_target, &words]
+    )?;
+    Ok(rows.iter().map(|row| (row.get("word"), row.get("gloss"))).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    fn entry(word: &str, gloss: &str) -> DictionaryEntry {
+        DictionaryEntry {
+            word: word.to_string(),
+            part_of_speech: None,
+            gloss: gloss.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_import_and_lookup() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let entries = vec![entry("unitdicthaus", "house"), entry("unitdicthaus", "home"), entry("unitdicthund", "dog")];
+        let dictionary = import_dictionary(&mut transaction, "unit test", "de", "en", "stardict", &entries).unwrap();
+        assert_eq!(dictionary.entries, 3);
+
+        let definitions = lookup(&mut transaction, "de", None, "unitdicthaus").unwrap();
+        let glosses: Vec<&str> = definitions.iter().map(|definition| definition.gloss.as_str()).collect();
+        assert_eq!(glosses, vec!["house", "home"]);
+        assert!(lookup(&mut transaction, "de", Some("pt"), "unitdicthaus").unwrap().is_empty());
+
+        let replaced = import_dictionary(&mut transaction, "unit test", "de", "en", "stardict", &entries[2..]).unwrap();
+        assert!(lookup(&mut transaction, "de", None, "unitdicthaus").unwrap().is_empty());
+
+        assert_eq!(delete_dictionary(&mut transaction, replaced.id).unwrap(), 1);
+        assert!(lookup(&mut transaction, "de", None, "unitdicthund").unwrap().is_empty());
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_get_glosses() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        import_dictionary(&mut transaction, "unit en", "de", "en", "wiktionary", &[entry("unitdicthaus", "house")]).unwrap();
+        import_dictionary(&mut transaction, "unit pt", "de", "pt", "wiktionary", &[entry("unitdicthaus", "casa")]).unwrap();
+
+        let words = vec!["unitdicthaus".to_string(), "unitdictkatze".to_string()];
+        let glosses = get_glosses(&mut transaction, "de", Some("pt"), &words).unwrap();
+        assert_eq!(glosses.len(), 1);
+        assert_eq!(glosses["unitdicthaus"], "casa");
+
+        let glosses = get_glosses(&mut transaction, "de", None, &words).unwrap();
+        assert_eq!(glosses["unitdicthaus"], "house");
+
+        transaction.rollback().unwrap();
+    }
+}
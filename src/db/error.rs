@@ -0,0 +1,37 @@
+use postgres::error::SqlState;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum UpdateError {
+    NotFound,
+    Conflict,
+    Db(postgres::Error),
+}
+
+impl UpdateError {
+    pub fn from_update(err: postgres::Error) -> UpdateError {
+        if err.code() == Some(&SqlState::UNIQUE_VIOLATION) {
+            UpdateError::Conflict
+        } else {
+            UpdateError::Db(err)
+        }
+    }
+}
+
+impl From<postgres::Error> for UpdateError {
+    fn from(err: postgres::Error) -> UpdateError {
+        UpdateError::Db(err)
+    }
+}
+
+impl fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UpdateError::NotFound => write!(f, "row not found"),
+            UpdateError::Conflict => write!(f, "unique constraint conflict"),
+            UpdateError::Db(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for UpdateError {}
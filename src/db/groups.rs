@@ -0,0 +1,418 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error, Row};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use crate::db::vocab_user;
+
+pub const ROLES: [&str; 2] = ["teacher", "student"];
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Group {
+    pub id: i32,
+    pub name: String,
+    pub role: String,
+    pub pending: bool,
+    pub teachers: i64,
+    pub students: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Member {
+    pub id: i32,
+    pub name: String,
+    pub role: String,
+    pub pending: bool,
+    pub joined_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct StudentProgress {
+    pub id: i32,
+    pub name: String,
+    pub known_words: i64,
+    pub learned_last_week: i64,
+    pub last_learned_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct WordKnowledge {
+    pub known_by: Vec<Member>,
+    pub unknown_to: Vec<Member>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct AssignedText {
+    pub id: i32,
+    pub title: Option<String>,
+    pub language: String,
+    pub assigned_at: DateTime<Utc>,
+}
+
+// `role` is the role of `id_user`, who must be a member or invited; `pending`
+// tells an invitation apart. Counts only include members who accepted.
+const GROUP_QUERY: &str = "
+    SELECT g.id, g.name, g.created_at, m.role, m.accepted_at IS NULL AS pending,
+           (SELECT count(*) FROM group_members t WHERE t.id_group = g.id AND t.role = 'teacher' AND t.accepted_at IS NOT NULL) AS teachers,
+           (SELECT count(*) FROM group_members s WHERE s.id_group = g.id AND s.role = 'student' AND s.accepted_at IS NOT NULL) AS students
+    FROM class_groups g
+    JOIN group_members m ON m.id_group = g.id AND m.id_user = $1";
+
+fn group_from_row(row: &Row) -> Group {
+    Group {
+        id: row.get("id"),
+        name: row.get("name"),
+        role: row.get("role"),
+        pending: row.get("pending"),
+        teachers: row.get("teachers"),
+        students: row.get("students"),
+        created_at: row.get("created_at"),
+    }
+}
+
+fn member_from_row(row: &Row) -> Member {
+    Member {
+        id: row.get("id"),
+        name: row.get("name"),
+        role: row.get("role"),
+        pending: row.get("pending"),
+        joined_at: row.get("joined_at"),
+    }
+}
+
+// The creator becomes the group's first teacher.
+pub fn create_group(client: &mut impl GenericClient, id_teacher: i32, name: &str) -> Result<Group, Error> {
+    let row = client.query_one(
+        "WITH created AS (
+             INSERT INTO class_groups (name) VALUES ($2) RETURNING id
+         )
+         INSERT INTO group_members (id_group, id_user, role, accepted_at)
+         SELECT id, $1, 'teacher', now() FROM created
+         RETURNING id_group",
+        &[&id_teacher, &name]
+    )?;
+    let id_group: i32 = row.get("id_group");
+
+    let row = client.query_one(&format!("{} WHERE g.id = $2", GROUP_QUERY), &[&id_teacher, &id_group])?;
+    Ok(group_from_row(&row))
+}
+
+// Groups the user belongs to, followed by pending invitations.
+pub fn get_groups_for_user(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<Group>, Error> {
+    let rows = client.query(&format!("{} ORDER BY pending, g.name, g.id", GROUP_QUERY), &[&id_user])?;
+    Ok(rows.iter().map(group_from_row).collect())
+}
+
+// Only members who accepted their invitation see the group.
+pub fn get_group(client: &mut impl GenericClient, id_user: i32, id_group: i32) -> Result<Option<Group>, Error> {
+    let row = client.query_opt(&format!("{} WHERE g.id = $2 AND m.accepted_at IS NOT NULL", GROUP_QUERY), &[&id_user, &id_group])?;
+    Ok(row.as_ref().map(group_from_row))
+}
+
+pub fn delete_group(client: &mut impl GenericClient, id_group: i32) -> Result<u64, Error> {
+    let count = client.execute("DELETE FROM class_groups WHERE id = $1", &[&id_group])?;
+    Ok(count)
+}
+
+pub fn get_members(client: &mut impl GenericClient, id_group: i32) -> Result<Vec<Member>, Error> {
+    let rows = client.query(
+        "SELECT u.id, u.name, m.role, m.accepted_at IS NULL AS pending, m.joined_at
+         FROM group_members m JOIN users u ON u.id = m.id_user
+         WHERE m.id_group = $1
+         ORDER BY m.role DESC, u.name",
+        &[&id_group]
+    )?;
+    Ok(rows.iter().map(member_from_row).collect())
+}
+
+// New members are only invited and join once they accept. Adding an
+// existing member changes their role; a teacher turned student must accept
+// again before the group sees their vocabulary.
+pub fn add_member(client: &mut impl GenericClient, id_group: i32, id_user: i32, role: &str) -> Result<u64, Error> {
+    let count = client.execute(
+        "INSERT INTO group_members (id_group, id_user, role) VALUES ($1, $2, $3)
+         ON CONFLICT (id_group, id_user) DO UPDATE SET
+             role = EXCLUDED.role,
+             accepted_at = CASE WHEN EXCLUDED.role = 'student' THEN NULL ELSE group_members.accepted_at END
+         WHERE group_members.role <> EXCLUDED.role",
+        &[&id_group, &id_user, &role]
+    )?;
+    Ok(count)
+}
+
+pub fn accept_invitation(client: &mut impl GenericClient, id_group: i32, id_user: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "UPDATE group_members SET accepted_at = now(), joined_at = now()
+         WHERE id_group = $1 AND id_user = $2 AND accepted_at IS NULL",
+        &[&id_group, &id_user]
+    )?;
+    Ok(count)
+}
+
+pub fn decline_invitation(client: &mut impl GenericClient, id_group: i32, id_user: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM group_members WHERE id_group = $1 AND id_user = $2 AND accepted_at IS NULL",
+        &[&id_group, &id_user]
+    )?;
+    Ok(count)
+}
+
+pub fn remove_member(client: &mut impl GenericClient, id_group: i32, id_user: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM group_members WHERE id_group = $1 AND id_user = $2",
+        &[&id_group, &id_user]
+    )?;
+    Ok(count)
+}
+
+pub fn get_student_progress(client: &mut impl GenericClient, id_group: i32, language: Option<&str>) -> Result<Vec<StudentProgress>, Error> {
+    let rows = client.query(
+        "SELECT u.id, u.name,
+                count(w.id) AS known_words,
+                count(w.id) FILTER (WHERE v.created_at >= now() - INTERVAL '7 days') AS learned_last_week,
+                max(v.created_at) FILTER (WHERE w.id IS NOT NULL) AS last_learned_at
+         FROM group_members m
+         JOIN users u ON u.id = m.id_user
+         LEFT JOIN vocabulary_user v ON v.id_user = m.id_user
+         LEFT JOIN words w ON w.id = v.id_word AND ($2::TEXT IS NULL OR w.language = $2)
+         WHERE m.id_group = $1 AND m.role = 'student' AND m.accepted_at IS NOT NULL
+         GROUP BY u.id, u.name
+         ORDER BY known_words DESC, u.name",
+        &[&id_group, &language]
+    )?;
+    let progress = rows.iter().map(|row| StudentProgress {
+        id: row.get("id"),
+        name: row.get("name"),
+        known_words: row.get("known_words"),
+        learned_last_week: row.get("learned_last_week"),
+        last_learned_at: row.get("last_learned_at"),
+    }).collect();
+    Ok(progress)
+}
+
+// Which students of the group know the word, and which do not. Invited
+// students are left out until they accept.
+pub fn get_word_knowledge(client: &mut impl GenericClient, id_group: i32, id_word: Option<i32>) -> Result<WordKnowledge, Error> {
+    let knowing: HashSet<i32> = match id_word {
+        Some(id_word) => vocab_user::get_users_for_word(client, id_word)?.into_iter().collect(),
+        None => HashSet::new(),
+    };
+
+    let (known_by, unknown_to) = get_members(client, id_group)?
+        .into_iter()
+        .filter(|member| member.role == "student" && !member.pending)
+        .partition(|member| knowing.contains(&member.id));
+
+    Ok(WordKnowledge { known_by, unknown_to })
+}
+
+// Number of the group's students who know each of `words`; words nobody
+// knows are left out.
+pub fn count_students_knowing(client: &mut impl GenericClient, id_group: i32, language: &str, words: &[String]) -> Result<HashMap<String, i64>, Error> {
+    if words.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = client.query(
+        "SELECT w.text, count(*) AS students
+         FROM group_members m
+         JOIN vocabulary_user v ON v.id_user = m.id_user
+         JOIN words w ON w.id = v.id_word
+         WHERE m.id_group = $1 AND m.role = 'student' AND m.accepted_at IS NOT NULL AND w.language = $2 AND w.text = ANY($3)
+         GROUP BY w.text",
+        &[&id_group, &language, &words]
+    )?;
+    Ok(rows.iter().map(|row| (row.get("text"), row.get("students"))).collect())
+}
+
+pub fn assign_text(client: &mut impl GenericClient, id_group: i32, id_text: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "INSERT INTO group_texts (id_group, id_text) VALUES ($1, $2)
+         ON CONFLICT (id_group, id_text) DO NOTHING",
+        &[&id_group, &id_text]
+    )?;
+    Ok(count)
+}
+
+pub fn unassign_text(client: &mut impl GenericClient, id_group: i32, id_text: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM group_texts WHERE id_group = $1 AND id_text = $2",
+        &[&id_group, &id_text]
+    )?;
+    Ok(count)
+}
+
+pub fn get_assigned_texts(client: &mut impl GenericClient, id_group: i32) -> Result<Vec<AssignedText>, Error> {
+    let rows = client.query(
+        "SELECT t.id, t.title, t.language, a.assigned_at
+         FROM group_texts a JOIN texts t ON t.id = a.id_text
+         WHERE a.id_group = $1
+         ORDER BY a.assigned_at DESC, t.id",
+        &[&id_group]
+    )?;
+    let texts = rows.iter().map(|row| AssignedText {
+        id: row.get("id"),
+        title: row.get("title"),
+        language: row.get("language"),
+        assigned_at: row.get("assigned_at"),
+    }).collect();
+    Ok(texts)
+}
+
+pub fn is_assigned(client: &mut impl GenericClient, id_group: i32, id_text: i32) -> Result<bool, Error> {
+    let row = client.query_opt(
+        "SELECT 1 FROM group_texts WHERE id_group = $1 AND id_text = $2",
+        &[&id_group, &id_text]
+    )?;
+    Ok(row.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::texts::insert_text;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::{insert_vocab_user, insert_vocab_users_with_dates};
+    use crate::db::words::insert_word;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    fn names(members: &[Member]) -> Vec<&str> {
+        members.iter().map(|member| member.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_group_members() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let teacher_id = insert_user(&mut client, "unitteacher").unwrap();
+        let student_id = insert_user(&mut client, "unitstudent").unwrap();
+
+        let group = create_group(&mut client, teacher_id, "Spanish A2").unwrap();
+        assert_eq!((group.role.as_str(), group.teachers, group.students), ("teacher", 1, 0));
+        assert!(get_group(&mut client, student_id, group.id).unwrap().is_none());
+
+        assert_eq!(add_member(&mut client, group.id, student_id, "student").unwrap(), 1);
+        assert_eq!(add_member(&mut client, group.id, student_id, "student").unwrap(), 0);
+        let invited = get_groups_for_user(&mut client, student_id).unwrap();
+        assert_eq!((invited[0].pending, invited[0].students), (true, 0));
+        assert!(get_group(&mut client, student_id, group.id).unwrap().is_none());
+
+        assert_eq!(accept_invitation(&mut client, group.id, student_id).unwrap(), 1);
+        assert_eq!(accept_invitation(&mut client, group.id, student_id).unwrap(), 0);
+        let joined = get_groups_for_user(&mut client, student_id).unwrap();
+        assert_eq!(joined.len(), 1);
+        assert_eq!((joined[0].role.as_str(), joined[0].pending, joined[0].students), ("student", false, 1));
+        assert_eq!(names(&get_members(&mut client, group.id).unwrap()), vec!["unitteacher", "unitstudent"]);
+        assert_eq!(decline_invitation(&mut client, group.id, student_id).unwrap(), 0);
+
+        assert_eq!(remove_member(&mut client, group.id, student_id).unwrap(), 1);
+        assert_eq!(delete_group(&mut client, group.id).unwrap(), 1);
+        assert!(get_groups_for_user(&mut client, teacher_id).unwrap().is_empty());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_student_vocabulary() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let teacher_id = insert_user(&mut client, "unitteacher").unwrap();
+        let ana_id = insert_user(&mut client, "unitana").unwrap();
+        let bruno_id = insert_user(&mut client, "unitbruno").unwrap();
+        let group = create_group(&mut client, teacher_id, "Spanish A2").unwrap();
+        for student_id in [ana_id, bruno_id] {
+            add_member(&mut client, group.id, student_id, "student").unwrap();
+            accept_invitation(&mut client, group.id, student_id).unwrap();
+        }
+
+        let casa_id = insert_word(&mut client, "unitgroupcasa", "es").unwrap();
+        let house_id = insert_word(&mut client, "unitgrouphouse", "en").unwrap();
+        insert_vocab_user(&mut client, ana_id, casa_id).unwrap();
+        insert_vocab_user(&mut client, ana_id, house_id).unwrap();
+        insert_vocab_user(&mut client, teacher_id, casa_id).unwrap();
+
+        let progress = get_student_progress(&mut client, group.id, Some("es")).unwrap();
+        let counts: Vec<(&str, i64)> = progress.iter().map(|student| (student.name.as_str(), student.known_words)).collect();
+        assert_eq!(counts, vec![("unitana", 1), ("unitbruno", 0)]);
+        assert!(progress[1].last_learned_at.is_none());
+        assert_eq!(get_student_progress(&mut client, group.id, None).unwrap()[0].known_words, 2);
+
+        let legacy_id = insert_word(&mut client, "unitgrouplegacy", "es").unwrap();
+        insert_vocab_users_with_dates(&mut client, bruno_id, &[(legacy_id, None)]).unwrap();
+        let bruno = get_student_progress(&mut client, group.id, Some("es")).unwrap().into_iter().find(|student| student.id == bruno_id).unwrap();
+        assert_eq!((bruno.known_words, bruno.learned_last_week, bruno.last_learned_at), (1, 0, None));
+
+        let knowledge = get_word_knowledge(&mut client, group.id, Some(casa_id)).unwrap();
+        assert_eq!(names(&knowledge.known_by), vec!["unitana"]);
+        assert_eq!(names(&knowledge.unknown_to), vec!["unitbruno"]);
+        assert_eq!(get_word_knowledge(&mut client, group.id, None).unwrap().unknown_to.len(), 2);
+
+        let words = vec!["unitgroupcasa".to_string(), "unitgroupperro".to_string()];
+        let counts = count_students_knowing(&mut client, group.id, "es", &words).unwrap();
+        assert_eq!(counts, HashMap::from([("unitgroupcasa".to_string(), 1)]));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_invited_students_stay_private() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let teacher_id = insert_user(&mut client, "unitteacher").unwrap();
+        let carla_id = insert_user(&mut client, "unitcarla").unwrap();
+        let group = create_group(&mut client, teacher_id, "Spanish A2").unwrap();
+        add_member(&mut client, group.id, carla_id, "student").unwrap();
+
+        let casa_id = insert_word(&mut client, "unitgroupcasa", "es").unwrap();
+        insert_vocab_user(&mut client, carla_id, casa_id).unwrap();
+
+        assert!(get_student_progress(&mut client, group.id, None).unwrap().is_empty());
+        let knowledge = get_word_knowledge(&mut client, group.id, Some(casa_id)).unwrap();
+        assert!(knowledge.known_by.is_empty() && knowledge.unknown_to.is_empty());
+        let words = vec!["unitgroupcasa".to_string()];
+        assert!(count_students_knowing(&mut client, group.id, "es", &words).unwrap().is_empty());
+
+        assert_eq!(decline_invitation(&mut client, group.id, carla_id).unwrap(), 1);
+        assert!(get_groups_for_user(&mut client, carla_id).unwrap().is_empty());
+
+        let teacher2_id = insert_user(&mut client, "unitteacher2").unwrap();
+        add_member(&mut client, group.id, teacher2_id, "teacher").unwrap();
+        accept_invitation(&mut client, group.id, teacher2_id).unwrap();
+        insert_vocab_user(&mut client, teacher2_id, casa_id).unwrap();
+        add_member(&mut client, group.id, teacher2_id, "student").unwrap();
+        assert!(get_student_progress(&mut client, group.id, None).unwrap().is_empty());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_assigned_texts() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let teacher_id = insert_user(&mut client, "unitteacher").unwrap();
+        let group = create_group(&mut client, teacher_id, "Spanish A2").unwrap();
+        let text_id = insert_text(&mut client, teacher_id, "es", Some("Lección 1"), "La casa").unwrap();
+
+        assert_eq!(assign_text(&mut client, group.id, text_id).unwrap(), 1);
+        assert_eq!(assign_text(&mut client, group.id, text_id).unwrap(), 0);
+        assert!(is_assigned(&mut client, group.id, text_id).unwrap());
+
+        let texts = get_assigned_texts(&mut client, group.id).unwrap();
+        assert_eq!(texts[0].title.as_deref(), Some("Lección 1"));
+
+        assert_eq!(unassign_text(&mut client, group.id, text_id).unwrap(), 1);
+        assert!(get_assigned_texts(&mut client, group.id).unwrap().is_empty());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
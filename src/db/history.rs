@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error, Row};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct VocabularyEvent {
+    pub id: i32,
+    pub word: String,
+    pub language: String,
+    pub status: String,
+    pub reverts: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ReplayReport {
+    pub added: u64,
+    pub removed: u64,
+}
+
+fn event_from_row(row: &Row) -> VocabularyEvent {
+    VocabularyEvent {
+        id: row.get("id"),
+        word: row.get("text"),
+        language: row.get("language"),
+        status: row.get("status"),
+        reverts: row.get("reverts"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub fn get_history(client: &mut impl GenericClient, id_user: i32, id_word: Option<i32>, limit: i64) -> Result<Vec<VocabularyEvent>, Error> {
+    let rows = client.query(
+        "SELECT e.id, w.text, w.language, e.status, e.reverts, e.created_at
+         FROM vocabulary_events e JOIN words w ON w.id = e.id_word
+         WHERE e.id_user = $1 AND ($2::INT IS NULL OR e.id_word = $2)
+         ORDER BY e.id DESC
+         LIMIT $3",
+        &[&id_user, &id_word, &limit]
+    )?;
+    Ok(rows.iter().map(event_from_row).collect())
+}
+
+// Undoing appends a reverting event instead of removing the original, so the
+// log stays append-only. Events that were already reverted, and the reverting
+// events themselves, are skipped: repeated undos walk further back in time.
+pub fn undo_last(client: &mut impl GenericClient, id_user: i32, count: i64) -> Result<Vec<VocabularyEvent>, Error> {
+    let mut transaction = client.transaction()?;
+
+    let rows = transaction.query(
+        "SELECT e.id, e.id_word, e.status
+         FROM vocabulary_events e
+         WHERE e.id_user = $1 AND e.reverts IS NULL
+           AND NOT EXISTS (SELECT 1 FROM vocabulary_events r WHERE r.reverts = e.id)
+         ORDER BY e.id DESC
+         LIMIT $2",
+        &[&id_user, &count]
+    )?;
+
+    let mut reverting_ids = Vec::new();
+    for row in rows {
+        let id: i32 = row.get("id");
+        let id_word: i32 = row.get("id_word");
+        let status: String = row.get("status");
+
+        let inverse = if status == "known" {
+            transaction.execute(
+                "DELETE FROM vocabulary_user WHERE id_user = $1 AND id_word = $2",
+                &[&id_user, &id_word]
+            )?;
+            "unknown"
+        } else {
+            // Bring back the date the word was originally learned, which
+            // stays unknown for vocabulary older than the event log.
+            transaction.execute(
+                "INSERT INTO vocabulary_user (id_user, id_word, created_at)
+                 SELECT $1, $2, CASE WHEN learned.id IS NULL THEN now() ELSE learned.created_at END
+                 FROM (VALUES (1)) AS one
+                 LEFT JOIN LATERAL (
+                     SELECT id, created_at FROM vocabulary_events
+                     WHERE id_user = $1 AND id_word = $2 AND status = 'known' AND id < $3
+                     ORDER BY id DESC
+                     LIMIT 1
+                 ) learned ON true
+                 ON CONFLICT (id_user, id_word) DO NOTHING",
+                &[&id_user, &id_word, &id]
+            )?;
+            "known"
+        };
+
+        let reverting = transaction.query_one(
+            "INSERT INTO vocabulary_events (id_user, id_word, status, reverts) VALUES ($1, $2, $3, $4) RETURNING id",
+            &[&id_user, &id_word, &inverse, &id]
+        )?;
+        reverting_ids.push(reverting.get::<_, i32>("id"));
+    }
+
+    let rows = transaction.query(
+        "SELECT e.id, w.text, w.language, e.status, e.reverts, e.created_at
+         FROM vocabulary_events e JOIN words w ON w.id = e.id_word
+         WHERE e.id = ANY($1)
+         ORDER BY e.id",
+        &[&reverting_ids]
+    )?;
+    let events = rows.iter().map(event_from_row).collect();
+
+    transaction.commit()?;
+
+    Ok(events)
+}
+
+// Rebuilds the user's vocabulary from the log: a word is known when its most
+// recent event says so, and keeps the date of that event.
+pub fn replay(client: &mut impl GenericClient, id_user: i32) -> Result<ReplayReport, Error> {
+    let mut transaction = client.transaction()?;
+
+    transaction.execute(
+        "CREATE TEMP TABLE replayed ON COMMIT DROP AS
+         SELECT DISTINCT ON (id_word) id_word, status, created_at
+         FROM vocabulary_events
+         WHERE id_user = $1
+         ORDER BY id_word, id DESC",
+        &[&id_user]
+    )?;
+
+    let removed = transaction.execute(
+        "DELETE FROM vocabulary_user v
+         WHERE v.id_user = $1
+           AND NOT EXISTS (SELECT 1 FROM replayed r WHERE r.id_word = v.id_word AND r.status = 'known')",
+        &[&id_user]
+    )?;
+
+    let added = transaction.execute(
+        "INSERT INTO vocabulary_user (id_user, id_word, created_at)
+         SELECT $1, id_word, created_at FROM replayed WHERE status = 'known'
+         ON CONFLICT (id_user, id_word) DO NOTHING",
+        &[&id_user]
+    )?;
+
+    transaction.commit()?;
+
+    Ok(ReplayReport { added, removed })
+}
+
+// Appends events for every user whose vocabulary disagrees with the latest
+// logged status of the word, after rows were moved or copied behind the
+// log's back (merging and splitting words).
+pub fn sync_events(client: &mut impl GenericClient, id_word: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "WITH latest AS (
+             SELECT DISTINCT ON (id_user) id_user, status
+             FROM vocabulary_events
+             WHERE id_word = $1
+             ORDER BY id_user, id DESC
+         ), drift AS (
+             SELECT v.id_user, 'known' AS status
+             FROM vocabulary_user v LEFT JOIN latest l ON l.id_user = v.id_user
+             WHERE v.id_word = $1 AND l.status IS DISTINCT FROM 'known'
+             UNION ALL
+             SELECT l.id_user, 'unknown'
+             FROM latest l
+             WHERE l.status = 'known'
+               AND NOT EXISTS (SELECT 1 FROM vocabulary_user v WHERE v.id_user = l.id_user AND v.id_word = $1)
+         )
+         INSERT INTO vocabulary_events (id_user, id_word, status)
+         SELECT id_user, $1, status FROM drift",
+        &[&id_word]
+    )?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::{delete_vocab_user, get_vocabulary_for_user, get_words_for_user, insert_vocab_user, insert_vocab_users_with_dates};
+    use crate::db::words::{insert_word, merge_words};
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    fn statuses(events: &[VocabularyEvent]) -> Vec<&str> {
+        events.iter().map(|event| event.status.as_str()).collect()
+    }
+
+    #[test]
+    fn test_history_records_changes() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let word_id = insert_word(&mut client, "unithistory", "en").unwrap();
+
+        insert_vocab_user(&mut client, user_id, word_id).unwrap();
+        insert_vocab_user(&mut client, user_id, word_id).unwrap();
+        delete_vocab_user(&mut client, user_id, word_id).unwrap();
+        delete_vocab_user(&mut client, user_id, word_id).unwrap();
+
+        let history = get_history(&mut client, user_id, Some(word_id), 10).unwrap();
+        assert_eq!(statuses(&history), vec!["unknown", "known"]);
+        assert_eq!(history[0].word, "unithistory");
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_undo_last() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let first_id = insert_word(&mut transaction, "unitundo1", "en").unwrap();
+        let second_id = insert_word(&mut transaction, "unitundo2", "en").unwrap();
+
+        insert_vocab_user(&mut transaction, user_id, first_id).unwrap();
+        insert_vocab_user(&mut transaction, user_id, second_id).unwrap();
+        delete_vocab_user(&mut transaction, user_id, first_id).unwrap();
+
+        let undone = undo_last(&mut transaction, user_id, 2).unwrap();
+        assert_eq!(undone.len(), 2);
+        assert!(undone.iter().all(|event| event.reverts.is_some()));
+        assert_eq!(get_words_for_user(&mut transaction, user_id).unwrap(), vec![first_id]);
+
+        let undone = undo_last(&mut transaction, user_id, 5).unwrap();
+        assert_eq!(statuses(&undone), vec!["unknown"]);
+        assert!(get_words_for_user(&mut transaction, user_id).unwrap().is_empty());
+        assert!(undo_last(&mut transaction, user_id, 1).unwrap().is_empty());
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_undo_keeps_unknown_dates() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let word_id = insert_word(&mut transaction, "unitundolegacy", "en").unwrap();
+        insert_vocab_users_with_dates(&mut transaction, user_id, &[(word_id, None)]).unwrap();
+        delete_vocab_user(&mut transaction, user_id, word_id).unwrap();
+
+        undo_last(&mut transaction, user_id, 1).unwrap();
+        let vocabulary = get_vocabulary_for_user(&mut transaction, user_id, None).unwrap();
+        assert_eq!(vocabulary.len(), 1);
+        assert_eq!(vocabulary[0].created_at, None);
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_replay() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let kept_id = insert_word(&mut transaction, "unitreplay1", "en").unwrap();
+        let dropped_id = insert_word(&mut transaction, "unitreplay2", "en").unwrap();
+        insert_vocab_user(&mut transaction, user_id, kept_id).unwrap();
+
+        transaction.execute("DELETE FROM vocabulary_user WHERE id_user = $1", &[&user_id]).unwrap();
+        transaction.execute("INSERT INTO vocabulary_user (id_user, id_word) VALUES ($1, $2)", &[&user_id, &dropped_id]).unwrap();
+
+        let report = replay(&mut transaction, user_id).unwrap();
+        assert_eq!(report, ReplayReport { added: 1, removed: 1 });
+        assert_eq!(get_words_for_user(&mut transaction, user_id).unwrap(), vec![kept_id]);
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_merge_keeps_history() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let from_id = insert_word(&mut transaction, "unitmergefrom", "en").unwrap();
+        let into_id = insert_word(&mut transaction, "unitmergeinto", "en").unwrap();
+        insert_vocab_user(&mut transaction, user_id, into_id).unwrap();
+        delete_vocab_user(&mut transaction, user_id, into_id).unwrap();
+        insert_vocab_user(&mut transaction, user_id, from_id).unwrap();
+
+        merge_words(&mut transaction, from_id, into_id, None).unwrap();
+
+        let history = get_history(&mut transaction, user_id, Some(into_id), 10).unwrap();
+        assert_eq!(statuses(&history), vec!["known", "unknown", "known"]);
+        assert_eq!(replay(&mut transaction, user_id).unwrap(), ReplayReport { added: 0, removed: 0 });
+
+        transaction.rollback().unwrap();
+    }
+}
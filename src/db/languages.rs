@@ -0,0 +1,82 @@
+use postgres::{GenericClient, Error, Row};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Language {
+    pub code: String,
+    pub name: String,
+    pub native_name: String,
+    pub script: String,
+}
+
+fn language_from_row(row: &Row) -> Language {
+    Language {
+        code: row.get("code"),
+        name: row.get("name"),
+        native_name: row.get("native_name"),
+        script: row.get("script"),
+    }
+}
+
+pub fn get_languages(client: &mut impl GenericClient) -> Result<Vec<Language>, Error> {
+    let rows = client.query(
+        "SELECT code, name, native_name, script FROM languages ORDER BY code",
+        &[]
+    )?;
+    Ok(rows.iter().map(language_from_row).collect())
+}
+
+pub fn get_language(client: &mut impl GenericClient, code: &str) -> Result<Option<Language>, Error> {
+    let row = client.query_opt(
+        "SELECT code, name, native_name, script FROM languages WHERE code = $1",
+        &[&code]
+    )?;
+    Ok(row.as_ref().map(language_from_row))
+}
+
+pub fn find_language_code(client: &mut impl GenericClient, value: &str) -> Result<Option<String>, Error> {
+    let row = client.query_opt(
+        "SELECT code FROM languages
+         WHERE lower(trim($1)) IN (code, lower(name), lower(native_name))
+         ORDER BY code = lower(trim($1)) DESC
+         LIMIT 1",
+        &[&value]
+    )?;
+    Ok(row.map(|row| row.get("code")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_get_language() {
+        let mut client = connect_test_client();
+
+        let language = get_language(&mut client, "pt").unwrap().unwrap();
+        assert_eq!(language.name, "Portuguese");
+        assert_eq!(language.native_name, "Português");
+        assert_eq!(language.script, "Latn");
+
+        assert!(get_language(&mut client, "english").unwrap().is_none());
+        assert!(get_languages(&mut client).unwrap().iter().any(|language| language.code == "ja"));
+    }
+
+    #[test]
+    fn test_find_language_code() {
+        let mut client = connect_test_client();
+
+        assert_eq!(find_language_code(&mut client, "en").unwrap().as_deref(), Some("en"));
+        assert_eq!(find_language_code(&mut client, "English").unwrap().as_deref(), Some("en"));
+        assert_eq!(find_language_code(&mut client, " português ").unwrap().as_deref(), Some("pt"));
+        assert_eq!(find_language_code(&mut client, "Deutsch").unwrap().as_deref(), Some("de"));
+        assert_eq!(find_language_code(&mut client, "klingon").unwrap(), None);
+    }
+}
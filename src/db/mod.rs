@@ -1,10 +1,22 @@
 pub mod users;
 pub mod words;
 pub mod vocab_user;
+pub mod texts;
+pub mod sessions;
+pub mod api_tokens;
+pub mod error;
+pub mod word_audit;
+pub mod languages;
+pub mod profiles;
+pub mod history;
+pub mod dictionaries;
+pub mod annotations;
+pub mod word_lists;
+pub mod groups;
 
-use postgres::{Client, NoTls, Error}; // W: unused import: `NoTls`
+use postgres::{Client, Error};
 
-pub fn init_tables(client: &mut Client) -> Result<(), Error> { // W: function `init_tables` is never used
+pub fn init_tables(client: &mut Client) -> Result<(), Error> {
     client.batch_execute("
         CREATE TABLE IF NOT EXISTS users (
             id SERIAL PRIMARY KEY,
@@ -12,6 +24,65 @@ pub fn init_tables(client: &mut Client) -> Result<(), Error> { // W: function `i
         )
     ")?;
 
+    client.batch_execute("
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS password_hash TEXT
+    ")?;
+
+    client.batch_execute("
+        ALTER TABLE users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT false
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS sessions (
+            token_hash TEXT PRIMARY KEY,
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            expires_at TIMESTAMPTZ NOT NULL DEFAULT now() + INTERVAL '30 days'
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS api_tokens (
+            id SERIAL PRIMARY KEY,
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            token_hash TEXT NOT NULL UNIQUE,
+            scope TEXT NOT NULL CHECK (scope IN ('read', 'read_write')),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS languages (
+            code TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            native_name TEXT NOT NULL,
+            script TEXT NOT NULL
+        )"
+    )?;
+
+    // Tokenization is the same for every language, so there are no
+    // per-language settings to keep.
+    client.batch_execute("
+        ALTER TABLE languages DROP COLUMN IF EXISTS segmentation, DROP COLUMN IF EXISTS word_pattern
+    ")?;
+
+    client.batch_execute("
+        INSERT INTO languages (code, name, native_name, script) VALUES
+            ('ar', 'Arabic', 'العربية', 'Arab'),
+            ('de', 'German', 'Deutsch', 'Latn'),
+            ('en', 'English', 'English', 'Latn'),
+            ('es', 'Spanish', 'Español', 'Latn'),
+            ('fr', 'French', 'Français', 'Latn'),
+            ('it', 'Italian', 'Italiano', 'Latn'),
+            ('ja', 'Japanese', '日本語', 'Jpan'),
+            ('ko', 'Korean', '한국어', 'Kore'),
+            ('nl', 'Dutch', 'Nederlands', 'Latn'),
+            ('pt', 'Portuguese', 'Português', 'Latn'),
+            ('ru', 'Russian', 'Русский', 'Cyrl'),
+            ('zh', 'Chinese', '中文', 'Hans')
+        ON CONFLICT (code) DO NOTHING
+    ")?;
+
     client.batch_execute("
         CREATE TABLE IF NOT EXISTS words(
             id SERIAL PRIMARY KEY,
@@ -23,12 +94,300 @@ pub fn init_tables(client: &mut Client) -> Result<(), Error> { // W: function `i
 
     client.batch_execute("
         CREATE TABLE IF NOT EXISTS vocabulary_user (
-            id_user INT REFERENCES users(id),
+            id_user INT REFERENCES users(id) ON DELETE CASCADE,
             id_word INT REFERENCES words(id),
             PRIMARY KEY (id_user, id_word)
         )"
     )?;
 
+    // Only older databases lack the cascade; rebuilding the key locks and
+    // rescans the whole table, so it is skipped once in place.
+    client.batch_execute("
+        DO $$
+        BEGIN
+            IF EXISTS (
+                SELECT 1 FROM pg_constraint
+                WHERE conname = 'vocabulary_user_id_user_fkey' AND confdeltype = 'c'
+            ) THEN
+                RETURN;
+            END IF;
+
+            ALTER TABLE vocabulary_user
+                DROP CONSTRAINT IF EXISTS vocabulary_user_id_user_fkey,
+                ADD CONSTRAINT vocabulary_user_id_user_fkey
+                    FOREIGN KEY (id_user) REFERENCES users(id) ON DELETE CASCADE;
+        END
+        $$
+    ")?;
+
+    // Vocabulary older than this column has no known date and keeps NULL;
+    // the default only applies to new rows.
+    client.batch_execute("
+        ALTER TABLE vocabulary_user ADD COLUMN IF NOT EXISTS created_at TIMESTAMPTZ;
+        ALTER TABLE vocabulary_user ALTER COLUMN created_at DROP NOT NULL, ALTER COLUMN created_at SET DEFAULT now();
+    ")?;
+
+    // Rows are only inserted or deleted, with every change logged in
+    // vocabulary_events, so there is no update time to keep.
+    client.batch_execute("
+        ALTER TABLE vocabulary_user DROP COLUMN IF EXISTS updated_at
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS texts (
+            id SERIAL PRIMARY KEY,
+            id_user INT REFERENCES users(id) ON DELETE CASCADE,
+            language TEXT NOT NULL,
+            content TEXT NOT NULL
+        )"
+    )?;
+
+    client.batch_execute("
+        ALTER TABLE texts ADD COLUMN IF NOT EXISTS title TEXT
+    ")?;
+
+    // Looks up texts by content, so analysing the same text again reuses its row.
+    client.batch_execute("
+        CREATE INDEX IF NOT EXISTS texts_content ON texts (id_user, language, md5(content))
+    ")?;
+
+    migrate_language_codes(client)?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS language_profiles (
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            language TEXT NOT NULL REFERENCES languages(code),
+            native_language TEXT REFERENCES languages(code),
+            daily_goal INT NOT NULL DEFAULT 0 CHECK (daily_goal >= 0),
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (id_user, language)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS vocabulary_events (
+            id SERIAL PRIMARY KEY,
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            id_word INT NOT NULL REFERENCES words(id) ON DELETE CASCADE,
+            status TEXT NOT NULL CHECK (status IN ('known', 'unknown')),
+            reverts INT REFERENCES vocabulary_events(id) ON DELETE CASCADE,
+            created_at TIMESTAMPTZ DEFAULT now()
+        )"
+    )?;
+
+    client.batch_execute("
+        ALTER TABLE vocabulary_events ALTER COLUMN created_at DROP NOT NULL
+    ")?;
+
+    client.batch_execute("
+        CREATE INDEX IF NOT EXISTS vocabulary_events_user_word ON vocabulary_events (id_user, id_word)
+    ")?;
+
+    // Vocabulary known before the event log existed gets a single "known"
+    // event at the time it was learned, if known, so replaying the log
+    // reproduces it.
+    client.batch_execute("
+        INSERT INTO vocabulary_events (id_user, id_word, status, created_at)
+        SELECT v.id_user, v.id_word, 'known', v.created_at
+        FROM vocabulary_user v
+        WHERE NOT EXISTS (
+            SELECT 1 FROM vocabulary_events e WHERE e.id_user = v.id_user AND e.id_word = v.id_word
+        )
+        ORDER BY v.created_at NULLS FIRST
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS dictionaries (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            source_language TEXT NOT NULL REFERENCES languages(code),
+            target_language TEXT NOT NULL REFERENCES languages(code),
+            format TEXT NOT NULL CHECK (format IN ('stardict', 'wiktionary')),
+            entries INT NOT NULL DEFAULT 0,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            UNIQUE (name, source_language, target_language)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS dictionary_entries (
+            id_dictionary INT NOT NULL REFERENCES dictionaries(id) ON DELETE CASCADE,
+            position INT NOT NULL,
+            word TEXT NOT NULL,
+            part_of_speech TEXT,
+            gloss TEXT NOT NULL,
+            PRIMARY KEY (id_dictionary, position)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE INDEX IF NOT EXISTS dictionary_entries_word ON dictionary_entries (word)
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS word_annotations (
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            id_word INT NOT NULL REFERENCES words(id) ON DELETE CASCADE,
+            translation TEXT,
+            note TEXT,
+            tags TEXT[] NOT NULL DEFAULT '{}',
+            search TSVECTOR GENERATED ALWAYS AS (
+                to_tsvector('simple', coalesce(translation, '') || ' ' || coalesce(note, ''))
+            ) STORED,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (id_user, id_word)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE INDEX IF NOT EXISTS word_annotations_search ON word_annotations USING GIN (search)
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS word_lists (
+            id SERIAL PRIMARY KEY,
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            name TEXT NOT NULL,
+            description TEXT,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            UNIQUE (id_user, name)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS word_list_entries (
+            id_list INT NOT NULL REFERENCES word_lists(id) ON DELETE CASCADE,
+            id_word INT NOT NULL REFERENCES words(id) ON DELETE CASCADE,
+            added_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (id_list, id_word)
+        )"
+    )?;
+
+    client.batch_execute("
+        ALTER TABLE word_lists ADD COLUMN IF NOT EXISTS is_public BOOLEAN NOT NULL DEFAULT false
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS word_list_subscriptions (
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            id_list INT NOT NULL REFERENCES word_lists(id) ON DELETE CASCADE,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (id_user, id_list)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS class_groups (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS group_members (
+            id_group INT NOT NULL REFERENCES class_groups(id) ON DELETE CASCADE,
+            id_user INT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            role TEXT NOT NULL CHECK (role IN ('teacher', 'student')),
+            joined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (id_group, id_user)
+        )"
+    )?;
+
+    // Members are invited and only join once they accept. Teachers already in
+    // a group created it or were added by one; students never agreed.
+    client.batch_execute("
+        DO $$
+        BEGIN
+            IF EXISTS (
+                SELECT 1 FROM information_schema.columns
+                WHERE table_name = 'group_members' AND column_name = 'accepted_at'
+            ) THEN
+                RETURN;
+            END IF;
+
+            ALTER TABLE group_members ADD COLUMN accepted_at TIMESTAMPTZ;
+            UPDATE group_members SET accepted_at = joined_at WHERE role = 'teacher';
+        END
+        $$
+    ")?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS group_texts (
+            id_group INT NOT NULL REFERENCES class_groups(id) ON DELETE CASCADE,
+            id_text INT NOT NULL REFERENCES texts(id) ON DELETE CASCADE,
+            assigned_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+            PRIMARY KEY (id_group, id_text)
+        )"
+    )?;
+
+    client.batch_execute("
+        CREATE TABLE IF NOT EXISTS word_audit (
+            id SERIAL PRIMARY KEY,
+            action TEXT NOT NULL CHECK (action IN ('merge', 'split')),
+            id_actor INT REFERENCES users(id) ON DELETE SET NULL,
+            source_id INT NOT NULL,
+            source_text TEXT NOT NULL,
+            source_language TEXT NOT NULL,
+            target_id INT NOT NULL,
+            target_text TEXT NOT NULL,
+            target_language TEXT NOT NULL,
+            affected_rows BIGINT NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+        )"
+    )?;
+
     Ok(())
 }
 
+// Older databases stored free-text language names ("english", "English",
+// "português"). Map them to ISO 639 codes, folding words that become
+// duplicates into a single row, before adding the foreign keys. Names that
+// match no language abort the migration and are listed, so they can be
+// added to `languages` with their real code first.
+fn migrate_language_codes(client: &mut Client) -> Result<(), Error> {
+    client.batch_execute("
+        DO $$
+        BEGIN
+            IF EXISTS (SELECT 1 FROM pg_constraint WHERE conname = 'words_language_fkey') THEN
+                RETURN;
+            END IF;
+
+            CREATE TEMP TABLE language_map ON COMMIT DROP AS
+                SELECT DISTINCT ON (old.language) old.language AS old, l.code
+                FROM (SELECT language FROM words UNION SELECT language FROM texts) old
+                LEFT JOIN languages l ON lower(trim(old.language)) IN (l.code, lower(l.name), lower(l.native_name))
+                ORDER BY old.language, l.code = lower(trim(old.language)) DESC;
+
+            IF EXISTS (SELECT 1 FROM language_map WHERE code IS NULL) THEN
+                RAISE EXCEPTION 'Unknown languages in words or texts: %. Add them to the languages table with their ISO 639 code and restart.',
+                    (SELECT string_agg(quote_literal(old), ', ' ORDER BY old) FROM language_map WHERE code IS NULL);
+            END IF;
+
+            CREATE TEMP TABLE word_map ON COMMIT DROP AS
+                SELECT w.id, first_value(w.id) OVER (
+                    PARTITION BY w.text, m.code ORDER BY w.language = m.code DESC, w.id
+                ) AS keep
+                FROM words w JOIN language_map m ON m.old = w.language;
+
+            INSERT INTO vocabulary_user (id_user, id_word, created_at)
+                SELECT v.id_user, wm.keep, v.created_at
+                FROM vocabulary_user v JOIN word_map wm ON wm.id = v.id_word
+                WHERE wm.id <> wm.keep
+                ON CONFLICT (id_user, id_word) DO NOTHING;
+            DELETE FROM vocabulary_user WHERE id_word IN (SELECT id FROM word_map WHERE id <> keep);
+            DELETE FROM words WHERE id IN (SELECT id FROM word_map WHERE id <> keep);
+
+            UPDATE words w SET language = m.code FROM language_map m WHERE m.old = w.language AND w.language <> m.code;
+            UPDATE texts t SET language = m.code FROM language_map m WHERE m.old = t.language AND t.language <> m.code;
+
+            ALTER TABLE words ADD CONSTRAINT words_language_fkey FOREIGN KEY (language) REFERENCES languages(code);
+            ALTER TABLE texts ADD CONSTRAINT texts_language_fkey FOREIGN KEY (language) REFERENCES languages(code);
+        END
+        $$
+    ")?;
+
+    Ok(())
+}
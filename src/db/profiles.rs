@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error, Row};
+use serde::Serialize;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LanguageProfile {
+    pub language: String,
+    pub native_language: Option<String>,
+    pub daily_goal: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn profile_from_row(row: &Row) -> LanguageProfile {
+    LanguageProfile {
+        language: row.get("language"),
+        native_language: row.get("native_language"),
+        daily_goal: row.get("daily_goal"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+pub fn upsert_profile(client: &mut impl GenericClient, id_user: i32, language: &str, native_language: Option<&str>, daily_goal: i32) -> Result<LanguageProfile, Error> {
+    let row = client.query_one(
+        "INSERT INTO language_profiles (id_user, language, native_language, daily_goal)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (id_user, language) DO UPDATE
+         SET native_language = EXCLUDED.native_language, daily_goal = EXCLUDED.daily_goal, updated_at = now()
+         RETURNING language, native_language, daily_goal, created_at, updated_at",
+        &[&id_user, &language, &native_language, &daily_goal]
+    )?;
+    Ok(profile_from_row(&row))
+}
+
+// Keeps the user's current profile for the language when there is one.
+pub fn insert_profile(client: &mut impl GenericClient, id_user: i32, language: &str, native_language: Option<&str>, daily_goal: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "INSERT INTO language_profiles (id_user, language, native_language, daily_goal)
+         VALUES ($1, $2, $3, $4)
+         ON CONFLICT (id_user, language) DO NOTHING",
+        &[&id_user, &language, &native_language, &daily_goal]
+    )?;
+    Ok(count)
+}
+
+pub fn get_profile(client: &mut impl GenericClient, id_user: i32, language: &str) -> Result<Option<LanguageProfile>, Error> {
+    let row = client.query_opt(
+        "SELECT language, native_language, daily_goal, created_at, updated_at
+         FROM language_profiles WHERE id_user = $1 AND language = $2",
+        &[&id_user, &language]
+    )?;
+    Ok(row.as_ref().map(profile_from_row))
+}
+
+pub fn get_profiles_for_user(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<LanguageProfile>, Error> {
+    let rows = client.query(
+        "SELECT language, native_language, daily_goal, created_at, updated_at
+         FROM language_profiles WHERE id_user = $1 ORDER BY language",
+        &[&id_user]
+    )?;
+    Ok(rows.iter().map(profile_from_row).collect())
+}
+
+pub fn delete_profile(client: &mut impl GenericClient, id_user: i32, language: &str) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM language_profiles WHERE id_user = $1 AND language = $2",
+        &[&id_user, &language]
+    )?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_upsert_profile() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+
+        let profile = upsert_profile(&mut client, user_id, "de", Some("pt"), 10).unwrap();
+        assert_eq!(profile.language, "de");
+        assert_eq!(profile.native_language.as_deref(), Some("pt"));
+        assert_eq!(profile.daily_goal, 10);
+
+        let updated = upsert_profile(&mut client, user_id, "de", None, 20).unwrap();
+        assert_eq!(updated.native_language, None);
+        assert_eq!(updated.daily_goal, 20);
+        assert_eq!(updated.created_at, profile.created_at);
+
+        upsert_profile(&mut client, user_id, "en", Some("pt"), 5).unwrap();
+        let profiles = get_profiles_for_user(&mut client, user_id).unwrap();
+        let languages: Vec<&str> = profiles.iter().map(|profile| profile.language.as_str()).collect();
+        assert_eq!(languages, vec!["de", "en"]);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_delete_profile() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        upsert_profile(&mut client, user_id, "de", None, 0).unwrap();
+
+        assert_eq!(delete_profile(&mut client, user_id, "de").unwrap(), 1);
+        assert!(get_profile(&mut client, user_id, "de").unwrap().is_none());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
@@ -0,0 +1,89 @@
+use postgres::{GenericClient, Error};
+
+pub fn insert_session(client: &mut impl GenericClient, token_hash: &str, id_user: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "INSERT INTO sessions (token_hash, id_user) VALUES ($1, $2)",
+        &[&token_hash, &id_user]
+    )?;
+    Ok(count)
+}
+
+pub fn get_user_for_session(client: &mut impl GenericClient, token_hash: &str) -> Result<Option<i32>, Error> {
+    let row = client.query_opt(
+        "SELECT id_user FROM sessions WHERE token_hash = $1 AND expires_at > now()",
+        &[&token_hash]
+    )?;
+    Ok(row.map(|r| r.get("id_user")))
+}
+
+pub fn delete_session(client: &mut impl GenericClient, token_hash: &str) -> Result<u64, Error> {
+    let count = client.execute("DELETE FROM sessions WHERE token_hash = $1", &[&token_hash])?;
+    Ok(count)
+}
+
+pub fn delete_expired_sessions(client: &mut impl GenericClient) -> Result<u64, Error> {
+    let count = client.execute("DELETE FROM sessions WHERE expires_at <= now()", &[])?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_insert_session() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let count = insert_session(&mut client, "tokenhash", user_id).unwrap();
+        assert_eq!(count, 1);
+
+        let fetched = get_user_for_session(&mut client, "tokenhash").unwrap();
+        assert_eq!(fetched, Some(user_id));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_expired_session() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        insert_session(&mut client, "tokenhash", user_id).unwrap();
+        client.execute("UPDATE sessions SET expires_at = now() - INTERVAL '1 day' WHERE token_hash = $1", &[&"tokenhash"]).unwrap();
+
+        let fetched = get_user_for_session(&mut client, "tokenhash").unwrap();
+        assert_eq!(fetched, None);
+
+        let count = delete_expired_sessions(&mut client).unwrap();
+        assert!(count >= 1);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_delete_session() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        insert_session(&mut client, "tokenhash", user_id).unwrap();
+        let count = delete_session(&mut client, "tokenhash").unwrap();
+        assert_eq!(count, 1);
+
+        let fetched = get_user_for_session(&mut client, "tokenhash").unwrap();
+        assert_eq!(fetched, None);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
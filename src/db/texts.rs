@@ -0,0 +1,154 @@
+use postgres::{GenericClient, Error, Row};
+
+pub struct Text {
+    pub id: i32,
+    pub id_user: i32,
+    pub language: String,
+    pub title: Option<String>,
+    pub content: String,
+}
+
+fn text_from_row(row: &Row) -> Text {
+    Text {
+        id: row.get("id"),
+        id_user: row.get("id_user"),
+        language: row.get("language"),
+        title: row.get("title"),
+        content: row.get("content"),
+    }
+}
+
+pub fn insert_text(client: &mut impl GenericClient, id_user: i32, language: &str, title: Option<&str>, content: &str) -> Result<i32, Error> {
+    let row = client.query_one(
+        "INSERT INTO texts (id_user, language, title, content) VALUES ($1, $2, $3, $4) RETURNING id",
+        &[&id_user, &language, &title, &content]
+    )?;
+    Ok(row.get("id"))
+}
+
+// Returns the user's existing text with the same language and content, or
+// stores a new untitled one. The advisory lock keeps two concurrent requests
+// for the same user from both inserting.
+pub fn find_or_insert_text(client: &mut impl GenericClient, id_user: i32, language: &str, content: &str) -> Result<i32, Error> {
+    let mut transaction = client.transaction()?;
+    transaction.execute("SELECT pg_advisory_xact_lock($1)", &[&i64::from(id_user)])?;
+
+    let existing = transaction.query_opt(
+        "SELECT id FROM texts
+         WHERE id_user = $1 AND language = $2 AND md5(content) = md5($3) AND content = $3
+         ORDER BY id
+         LIMIT 1",
+        &[&id_user, &language, &content]
+    )?;
+    let id = match existing {
+        Some(row) => row.get("id"),
+        None => insert_text(&mut transaction, id_user, language, None, content)?,
+    };
+
+    transaction.commit()?;
+    Ok(id)
+}
+
+pub fn get_text(client: &mut impl GenericClient, id: i32) -> Result<Option<Text>, Error> {
+    let row = client.query_opt("SELECT id, id_user, language, title, content FROM texts WHERE id = $1", &[&id])?;
+    Ok(row.as_ref().map(text_from_row))
+}
+
+pub fn get_texts_for_user(client: &mut impl GenericClient, id_user: i32, language: Option<&str>) -> Result<Vec<Text>, Error> {
+    let rows = client.query(
+        "SELECT id, id_user, language, title, content FROM texts
+         WHERE id_user = $1 AND ($2::TEXT IS NULL OR language = $2)
+         ORDER BY id",
+        &[&id_user, &language]
+    )?;
+    Ok(rows.iter().map(text_from_row).collect())
+}
+
+pub fn delete_text(client: &mut impl GenericClient, id: i32) -> Result<u64, Error> {
+    let count = client.execute("DELETE FROM texts WHERE id = $1", &[&id])?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_insert_text() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let id = insert_text(&mut client, user_id, "en", None, "The cat sat.").unwrap();
+
+        let text = get_text(&mut client, id).unwrap().unwrap();
+        assert_eq!(text.id, id);
+        assert_eq!(text.id_user, user_id);
+        assert_eq!(text.language, "en");
+        assert_eq!(text.title, None);
+        assert_eq!(text.content, "The cat sat.");
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_find_or_insert_text() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let other_id = insert_user(&mut transaction, "otheruser").unwrap();
+        let id = find_or_insert_text(&mut transaction, user_id, "en", "The cat sat.").unwrap();
+
+        assert_eq!(find_or_insert_text(&mut transaction, user_id, "en", "The cat sat.").unwrap(), id);
+        assert_ne!(find_or_insert_text(&mut transaction, user_id, "en", "The cat ran.").unwrap(), id);
+        assert_ne!(find_or_insert_text(&mut transaction, user_id, "pt", "The cat sat.").unwrap(), id);
+        assert_ne!(find_or_insert_text(&mut transaction, other_id, "en", "The cat sat.").unwrap(), id);
+        assert_eq!(get_texts_for_user(&mut transaction, user_id, None).unwrap().len(), 3);
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_get_texts_for_user() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let id1 = insert_text(&mut client, user_id, "en", None, "The cat sat.").unwrap();
+        insert_text(&mut client, user_id, "pt", Some("Capítulo 1"), "O gato sentou.").unwrap();
+
+        let all = get_texts_for_user(&mut client, user_id, None).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let english = get_texts_for_user(&mut client, user_id, Some("en")).unwrap();
+        assert_eq!(english.len(), 1);
+        assert_eq!(english[0].id, id1);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_delete_text() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let id = insert_text(&mut client, user_id, "en", None, "The cat sat.").unwrap();
+        let count = delete_text(&mut client, id).unwrap();
+        assert_eq!(count, 1);
+
+        let text = get_text(&mut client, id).unwrap();
+        assert!(text.is_none());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
@@ -1,6 +1,12 @@
-use postgres::{Client, NoTls, Error};
+use postgres::{GenericClient, Error};
+use crate::db::error::UpdateError;
 
-pub fn insert_user(client: &mut Client, name: &str) -> Result<i32, Error> {
+pub struct User {
+    pub id: i32,
+    pub name: String,
+}
+
+pub fn insert_user(client: &mut impl GenericClient, name: &str) -> Result<i32, Error> {
     let insert = client.query_opt("INSERT INTO users(name) VALUES ($1) ON CONFLICT (name) DO NOTHING RETURNING id", &[&name])?;
 
     if let Some(row) = insert {
@@ -14,22 +20,47 @@ pub fn insert_user(client: &mut Client, name: &str) -> Result<i32, Error> {
     }
 }
 
-pub fn get_user_by_id(client: &mut Client, id: i32) -> Result<Option<String>, Error> {
+pub fn insert_user_with_password(client: &mut impl GenericClient, name: &str, password_hash: &str) -> Result<Option<i32>, Error> {
+    let row = client.query_opt(
+        "INSERT INTO users(name, password_hash) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING RETURNING id",
+        &[&name, &password_hash]
+    )?;
+    Ok(row.map(|r| r.get("id")))
+}
+
+pub fn get_user_by_id(client: &mut impl GenericClient, id: i32) -> Result<Option<String>, Error> {
     let row = client.query_opt("SELECT name FROM users WHERE id = $1", &[&id])?;
     Ok(row.map(|r| r.get("name")))
 }
 
-pub fn get_user_by_name(client: &mut Client, name: &str) -> Result<Option<i32>, Error> {
+pub fn get_user_by_name(client: &mut impl GenericClient, name: &str) -> Result<Option<i32>, Error> {
     let row = client.query_opt("SELECT id FROM users WHERE name = $1", &[&name])?;
     Ok(row.map(|r| r.get("id")))
 }
 
-pub fn update_user_name(client: &mut Client, id: i32, new_name: &str) -> Result<Option<i32>, Error> {
-    let row = client.query_opt("UPDATE users SET name = $1 WHERE id = $2", &[&new_name, &id])?;
-    Ok(row.map(|r| r.get("id")))
+pub fn get_credentials_by_name(client: &mut impl GenericClient, name: &str) -> Result<Option<(i32, Option<String>)>, Error> {
+    let row = client.query_opt("SELECT id, password_hash FROM users WHERE name = $1", &[&name])?;
+    Ok(row.map(|r| (r.get("id"), r.get("password_hash"))))
+}
+
+pub fn is_admin(client: &mut impl GenericClient, id: i32) -> Result<bool, Error> {
+    let row = client.query_opt("SELECT is_admin FROM users WHERE id = $1", &[&id])?;
+    Ok(row.is_some_and(|r| r.get("is_admin")))
 }
 
-pub fn delete_user(client: &mut Client, id: i32) -> Result<u64, Error> {
+pub fn update_user_name(client: &mut impl GenericClient, id: i32, new_name: &str) -> Result<User, UpdateError> {
+    let row = client
+        .query_opt("UPDATE users SET name = $1 WHERE id = $2 RETURNING id, name", &[&new_name, &id])
+        .map_err(UpdateError::from_update)?
+        .ok_or(UpdateError::NotFound)?;
+
+    Ok(User {
+        id: row.get("id"),
+        name: row.get("name"),
+    })
+}
+
+pub fn delete_user(client: &mut impl GenericClient, id: i32) -> Result<u64, Error> {
     let count = client.execute("DELETE FROM users WHERE id = $1", &[&id])?;
     Ok(count)
 }
@@ -37,7 +68,10 @@ pub fn delete_user(client: &mut Client, id: i32) -> Result<u64, Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use postgres::{Client, NoTls};
     use std::env;
+    use crate::db::vocab_user::{get_users_for_word, insert_vocab_user};
+    use crate::db::words::insert_word;
 
     fn connect_test_client() -> Client {
         let db_url = env::var("DATABASE_URL").unwrap();
@@ -51,13 +85,27 @@ mod tests {
 
         let new_user = "testuser";
 
-        let id = insert_user(&mut client, &new_user).unwrap();
+        let id = insert_user(&mut client, new_user).unwrap();
         let fetched_name = get_user_by_id(&mut client, id).unwrap();
         assert_eq!(fetched_name, Some(new_user.to_string()));
 
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_insert_user_with_password() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let id = insert_user_with_password(&mut client, "testuser", "hash").unwrap();
+        assert!(id.is_some());
+
+        let duplicate = insert_user_with_password(&mut client, "testuser", "other").unwrap();
+        assert_eq!(duplicate, None);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
     #[test]
     fn test_get_user_by_id() {
         let mut client = connect_test_client();
@@ -65,7 +113,7 @@ mod tests {
 
         let new_user = "testuser";
 
-        let id = insert_user(&mut client, &new_user).unwrap();
+        let id = insert_user(&mut client, new_user).unwrap();
         let row = get_user_by_id(&mut client, id).unwrap();
         assert_eq!(row, Some(new_user.to_string()));
 
@@ -79,13 +127,42 @@ mod tests {
 
         let new_user = "testuser";
 
-        let id = insert_user(&mut client, &new_user).unwrap();
-        let fetched = get_user_by_name(&mut client, &new_user).unwrap();
+        let id = insert_user(&mut client, new_user).unwrap();
+        let fetched = get_user_by_name(&mut client, new_user).unwrap();
         assert_eq!(fetched, Some(id));
 
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_get_credentials_by_name() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let id = insert_user_with_password(&mut client, "testuser", "hash").unwrap().unwrap();
+        let credentials = get_credentials_by_name(&mut client, "testuser").unwrap();
+        assert_eq!(credentials, Some((id, Some("hash".to_string()))));
+
+        let missing = get_credentials_by_name(&mut client, "nobody").unwrap();
+        assert_eq!(missing, None);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_is_admin() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let id = insert_user(&mut client, "testuser").unwrap();
+        assert!(!is_admin(&mut client, id).unwrap());
+
+        client.execute("UPDATE users SET is_admin = true WHERE id = $1", &[&id]).unwrap();
+        assert!(is_admin(&mut client, id).unwrap());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
     #[test]
     fn test_update_user_name() {
         let mut client = connect_test_client();
@@ -95,7 +172,9 @@ mod tests {
         let new_name = "Updated".to_string();
 
         let id = insert_user(&mut client, &old_name).unwrap();
-        update_user_name(&mut client, id, &new_name).unwrap();
+        let updated = update_user_name(&mut client, id, &new_name).unwrap();
+        assert_eq!(updated.id, id);
+        assert_eq!(updated.name, new_name);
 
         let updated_id = get_user_by_name(&mut client, &new_name).unwrap();
         assert_eq!(updated_id, Some(id));
@@ -103,6 +182,31 @@ mod tests {
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_update_user_name_conflict() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let id = insert_user(&mut client, "testuser").unwrap();
+        insert_user(&mut client, "otheruser").unwrap();
+
+        let result = update_user_name(&mut client, id, "otheruser");
+        assert!(matches!(result, Err(UpdateError::Conflict)));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_update_user_name_not_found() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let result = update_user_name(&mut client, -1, "nobody");
+        assert!(matches!(result, Err(UpdateError::NotFound)));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
     #[test]
     fn test_delete_user() {
         let mut client = connect_test_client();
@@ -115,4 +219,22 @@ mod tests {
 
         client.batch_execute("ROLLBACK").unwrap();
     }
+
+    #[test]
+    fn test_delete_user_cascades_vocabulary() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let id = insert_user(&mut client, "testuser").unwrap();
+        let word_id = insert_word(&mut client, "testword", "en").unwrap();
+        insert_vocab_user(&mut client, id, word_id).unwrap();
+
+        let count = delete_user(&mut client, id).unwrap();
+        assert_eq!(count, 1);
+
+        let users = get_users_for_word(&mut client, word_id).unwrap();
+        assert!(users.is_empty());
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
 }
@@ -1,50 +1,132 @@
-use postgres::{Client, NoTls, Error};
+use chrono::{DateTime, NaiveDate, Utc};
+use postgres::{GenericClient, Error};
+use std::collections::HashSet;
+
+pub struct VocabularyEntry {
+    pub word: String,
+    pub language: String,
+    pub created_at: Option<DateTime<Utc>>,
+}
 
-pub fn insert_vocab_user(client: &mut Client, id_user: i32, id_word: i32) -> Result<u64, Error> {
+pub fn insert_vocab_user(client: &mut impl GenericClient, id_user: i32, id_word: i32) -> Result<u64, Error> {
     let count = client.execute(
-        "INSERT INTO vocabulary_user (id_user, id_word) VALUES ($1, $2) ON CONFLICT (id_user, id_word) DO NOTHING",
+        "WITH inserted AS (
+             INSERT INTO vocabulary_user (id_user, id_word) VALUES ($1, $2)
+             ON CONFLICT (id_user, id_word) DO NOTHING
+             RETURNING id_user, id_word
+         )
+         INSERT INTO vocabulary_events (id_user, id_word, status)
+         SELECT id_user, id_word, 'known' FROM inserted",
         &[&id_user, &id_word]
     )?;
     Ok(count)
 }
 
-pub fn insert_vocab_users_multiple(client: &mut Client, id_user: i32, id_words: &[i32]) -> Result<u64, Error> {
-    if id_words.is_empty() {
-        return Ok(0);
-    }
-
-    let mut query = String::from("INSERT INTO vocabulary_user (id_user, id_word) VALUES ");
-    let mut params: Vec<&(dyn postgres::types::ToSql + Sync)> = Vec::new();
-    let mut placeholders = Vec::new();
-
-    for (i, id_word) in id_words.iter().enumerate() {
-        placeholders.push(format!("(${}, ${})", 2 * i + 1, 2 * i + 2));
-        params.push(&id_user as &(dyn postgres::types::ToSql + Sync));
-        params.push(id_word as &(dyn postgres::types::ToSql + Sync));
-    }
+pub fn insert_vocab_users_multiple(client: &mut impl GenericClient, id_user: i32, id_words: &[i32]) -> Result<u64, Error> {
+    let count = client.execute(
+        "WITH inserted AS (
+             INSERT INTO vocabulary_user (id_user, id_word)
+             SELECT $1, id_word FROM UNNEST($2::int[]) AS id_word
+             ON CONFLICT (id_user, id_word) DO NOTHING
+             RETURNING id_user, id_word
+         )
+         INSERT INTO vocabulary_events (id_user, id_word, status)
+         SELECT id_user, id_word, 'known' FROM inserted",
+        &[&id_user, &id_words]
+    )?;
+    Ok(count)
+}
 
-    query.push_str(&placeholders.join(", "));
-    query.push_str(" ON CONFLICT (id_user, id_word) DO NOTHING");
+// Entries without a date are stored as learned at an unknown time.
+pub fn insert_vocab_users_with_dates(client: &mut impl GenericClient, id_user: i32, entries: &[(i32, Option<DateTime<Utc>>)]) -> Result<u64, Error> {
+    let id_words: Vec<i32> = entries.iter().map(|(id_word, _)| *id_word).collect();
+    let dates: Vec<Option<DateTime<Utc>>> = entries.iter().map(|(_, date)| *date).collect();
 
-    let count = client.execute(&query, &params)?;
+    let count = client.execute(
+        "WITH inserted AS (
+             INSERT INTO vocabulary_user (id_user, id_word, created_at)
+             SELECT $1, * FROM UNNEST($2::int[], $3::timestamptz[])
+             ON CONFLICT (id_user, id_word) DO NOTHING
+             RETURNING id_user, id_word, created_at
+         )
+         INSERT INTO vocabulary_events (id_user, id_word, status, created_at)
+         SELECT id_user, id_word, 'known', created_at FROM inserted ORDER BY created_at NULLS FIRST",
+        &[&id_user, &id_words, &dates]
+    )?;
     Ok(count)
 }
 
-pub fn get_words_for_user(client: &mut Client, id_user: i32) -> Result<Vec<i32>, Error> {
+pub fn get_words_for_user(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<i32>, Error> {
     let rows = client.query("SELECT id_word FROM vocabulary_user WHERE id_user = $1", &[&id_user])?;
     let word_ids: Vec<i32> = rows.iter().map(|row| row.get("id_word")).collect();
     Ok(word_ids)
 }
 
-pub fn get_users_for_word(client: &mut Client, id_word: i32) -> Result<Vec<i32>, Error> {
+pub fn get_vocabulary_for_user(client: &mut impl GenericClient, id_user: i32, language: Option<&str>) -> Result<Vec<VocabularyEntry>, Error> {
+    let rows = client.query(
+        "SELECT w.text, w.language, v.created_at
+         FROM vocabulary_user v JOIN words w ON w.id = v.id_word
+         WHERE v.id_user = $1 AND ($2::TEXT IS NULL OR w.language = $2)
+         ORDER BY w.language, w.text",
+        &[&id_user, &language]
+    )?;
+    let entries = rows.iter().map(|row| VocabularyEntry {
+        word: row.get("text"),
+        language: row.get("language"),
+        created_at: row.get("created_at"),
+    }).collect();
+    Ok(entries)
+}
+
+pub fn get_known_words_among(client: &mut impl GenericClient, id_user: i32, language: &str, words: &[String]) -> Result<HashSet<String>, Error> {
+    let rows = client.query(
+        "SELECT w.text
+         FROM vocabulary_user v JOIN words w ON w.id = v.id_word
+         WHERE v.id_user = $1 AND w.language = $2 AND w.text = ANY($3)",
+        &[&id_user, &language, &words]
+    )?;
+    Ok(rows.iter().map(|row| row.get("text")).collect())
+}
+
+pub fn count_known_by_language(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<(String, i64)>, Error> {
+    let rows = client.query(
+        "SELECT w.language, count(*) AS known
+         FROM vocabulary_user v JOIN words w ON w.id = v.id_word
+         WHERE v.id_user = $1
+         GROUP BY w.language
+         ORDER BY w.language",
+        &[&id_user]
+    )?;
+    Ok(rows.iter().map(|row| (row.get("language"), row.get("known"))).collect())
+}
+
+// Words learned before dates were recorded belong to no day.
+pub fn count_learned_per_day(client: &mut impl GenericClient, id_user: i32, language: Option<&str>) -> Result<Vec<(NaiveDate, i64)>, Error> {
+    let rows = client.query(
+        "SELECT (v.created_at AT TIME ZONE 'UTC')::date AS day, count(*) AS learned
+         FROM vocabulary_user v JOIN words w ON w.id = v.id_word
+         WHERE v.id_user = $1 AND v.created_at IS NOT NULL AND ($2::TEXT IS NULL OR w.language = $2)
+         GROUP BY day
+         ORDER BY day",
+        &[&id_user, &language]
+    )?;
+    Ok(rows.iter().map(|row| (row.get("day"), row.get("learned"))).collect())
+}
+
+pub fn get_users_for_word(client: &mut impl GenericClient, id_word: i32) -> Result<Vec<i32>, Error> {
     let rows = client.query("SELECT id_user FROM vocabulary_user WHERE id_word = $1", &[&id_word])?;
     let user_ids: Vec<i32> = rows.iter().map(|row| row.get("id_user")).collect();
     Ok(user_ids)
 }
 
-pub fn delete_vocab_user(client: &mut Client, id_user: i32, id_word: i32) -> Result<u64, Error> {
+pub fn delete_vocab_user(client: &mut impl GenericClient, id_user: i32, id_word: i32) -> Result<u64, Error> {
     let count = client.execute(
-        "DELETE FROM vocabulary_user WHERE id_user = $1 AND id_word = $2",
+        "WITH deleted AS (
+             DELETE FROM vocabulary_user WHERE id_user = $1 AND id_word = $2
+             RETURNING id_user, id_word
+         )
+         INSERT INTO vocabulary_events (id_user, id_word, status)
+         SELECT id_user, id_word, 'unknown' FROM deleted",
         &[&id_user, &id_word]
     )?;
     Ok(count)
@@ -53,9 +135,10 @@ pub fn delete_vocab_user(client: &mut Client, id_user: i32, id_word: i32) -> Res
 #[cfg(test)]
 mod tests {
     use super::*;
+    use postgres::{Client, NoTls};
     use std::env;
     use crate::db::users::insert_user;
-    use crate::db::words::insert_word;
+    use crate::db::words::{insert_multiple_words, insert_word};
 
     fn connect_test_client() -> Client {
         let db_url = env::var("DATABASE_URL").unwrap();
@@ -68,7 +151,7 @@ mod tests {
         client.batch_execute("BEGIN").unwrap();
 
         let user_id = insert_user(&mut client, "testuser").unwrap();
-        let word_id = insert_word(&mut client, "testword", "english").unwrap();
+        let word_id = insert_word(&mut client, "testword", "en").unwrap();
 
         let count = insert_vocab_user(&mut client, user_id, word_id).unwrap();
         assert_eq!(count, 1);
@@ -85,8 +168,8 @@ mod tests {
         client.batch_execute("BEGIN").unwrap();
 
         let user_id = insert_user(&mut client, "testuser").unwrap();
-        let word_id1 = insert_word(&mut client, "word1", "english").unwrap();
-        let word_id2 = insert_word(&mut client, "word2", "english").unwrap();
+        let word_id1 = insert_word(&mut client, "word1", "en").unwrap();
+        let word_id2 = insert_word(&mut client, "word2", "en").unwrap();
         let word_ids = vec![word_id1, word_id2];
 
         let count = insert_vocab_users_multiple(&mut client, user_id, &word_ids).unwrap();
@@ -103,14 +186,54 @@ mod tests {
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_insert_vocab_users_multiple_many_words() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let texts: Vec<String> = (0..40_000).map(|i| format!("unitmany{}", i)).collect();
+        let word_ids = insert_multiple_words(&mut client, &texts, "en").unwrap();
+
+        let count = insert_vocab_users_multiple(&mut client, user_id, &word_ids).unwrap();
+        assert_eq!(count, 40_000);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_insert_vocab_users_with_dates() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let word_id = insert_word(&mut client, "word1", "en").unwrap();
+        let date = "2024-01-02T03:04:05Z".parse::<DateTime<Utc>>().unwrap();
+
+        let count = insert_vocab_users_with_dates(&mut client, user_id, &[(word_id, Some(date))]).unwrap();
+        assert_eq!(count, 1);
+
+        let count2 = insert_vocab_users_with_dates(&mut client, user_id, &[(word_id, Some(date))]).unwrap();
+        assert_eq!(count2, 0);
+
+        let undated_id = insert_word(&mut client, "word2", "en").unwrap();
+        insert_vocab_users_with_dates(&mut client, user_id, &[(undated_id, None)]).unwrap();
+
+        let vocabulary = get_vocabulary_for_user(&mut client, user_id, None).unwrap();
+        assert_eq!(vocabulary[0].created_at, Some(date));
+        assert_eq!(vocabulary[1].created_at, None);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
     #[test]
     fn test_get_words_for_user() {
         let mut client = connect_test_client();
         client.batch_execute("BEGIN").unwrap();
 
         let user_id = insert_user(&mut client, "testuser").unwrap();
-        let word_id1 = insert_word(&mut client, "word1", "english").unwrap();
-        let word_id2 = insert_word(&mut client, "word2", "english").unwrap();
+        let word_id1 = insert_word(&mut client, "word1", "en").unwrap();
+        let word_id2 = insert_word(&mut client, "word2", "en").unwrap();
 
         insert_vocab_user(&mut client, user_id, word_id1).unwrap();
         insert_vocab_user(&mut client, user_id, word_id2).unwrap();
@@ -123,6 +246,29 @@ mod tests {
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_get_vocabulary_for_user() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let word_id1 = insert_word(&mut client, "word1", "en").unwrap();
+        let word_id2 = insert_word(&mut client, "palavra", "pt").unwrap();
+
+        insert_vocab_user(&mut client, user_id, word_id1).unwrap();
+        insert_vocab_user(&mut client, user_id, word_id2).unwrap();
+
+        let all = get_vocabulary_for_user(&mut client, user_id, None).unwrap();
+        assert_eq!(all.len(), 2);
+
+        let english = get_vocabulary_for_user(&mut client, user_id, Some("en")).unwrap();
+        assert_eq!(english.len(), 1);
+        assert_eq!(english[0].word, "word1");
+        assert_eq!(english[0].language, "en");
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
     #[test]
     fn test_get_users_for_word() {
         let mut client = connect_test_client();
@@ -130,7 +276,7 @@ mod tests {
 
         let user_id1 = insert_user(&mut client, "user1").unwrap();
         let user_id2 = insert_user(&mut client, "user2").unwrap();
-        let word_id = insert_word(&mut client, "testword", "english").unwrap();
+        let word_id = insert_word(&mut client, "testword", "en").unwrap();
 
         insert_vocab_user(&mut client, user_id1, word_id).unwrap();
         insert_vocab_user(&mut client, user_id2, word_id).unwrap();
@@ -149,7 +295,7 @@ mod tests {
         client.batch_execute("BEGIN").unwrap();
 
         let user_id = insert_user(&mut client, "testuser").unwrap();
-        let word_id = insert_word(&mut client, "testword", "english").unwrap();
+        let word_id = insert_word(&mut client, "testword", "en").unwrap();
 
         insert_vocab_user(&mut client, user_id, word_id).unwrap();
         let count = delete_vocab_user(&mut client, user_id, word_id).unwrap();
@@ -160,4 +306,48 @@ mod tests {
 
         client.batch_execute("ROLLBACK").unwrap();
     }
+
+    #[test]
+    fn test_get_known_words_among() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let cat_id = insert_word(&mut client, "cat", "en").unwrap();
+        let gato_id = insert_word(&mut client, "gato", "pt").unwrap();
+        insert_vocab_user(&mut client, user_id, cat_id).unwrap();
+        insert_vocab_user(&mut client, user_id, gato_id).unwrap();
+
+        let words = vec!["cat".to_string(), "dog".to_string(), "gato".to_string()];
+        let known = get_known_words_among(&mut client, user_id, "en", &words).unwrap();
+        assert_eq!(known, HashSet::from(["cat".to_string()]));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_count_learned() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let cat_id = insert_word(&mut client, "cat", "en").unwrap();
+        let dog_id = insert_word(&mut client, "dog", "en").unwrap();
+        let gato_id = insert_word(&mut client, "gato", "pt").unwrap();
+
+        let monday = DateTime::parse_from_rfc3339("2024-01-01T10:00:00Z").unwrap().with_timezone(&Utc);
+        let tuesday = DateTime::parse_from_rfc3339("2024-01-02T23:30:00Z").unwrap().with_timezone(&Utc);
+        insert_vocab_users_with_dates(&mut client, user_id, &[(cat_id, Some(monday)), (dog_id, Some(tuesday)), (gato_id, Some(tuesday))]).unwrap();
+
+        let by_language = count_known_by_language(&mut client, user_id).unwrap();
+        assert_eq!(by_language, vec![("en".to_string(), 2), ("pt".to_string(), 1)]);
+
+        let per_day = count_learned_per_day(&mut client, user_id, None).unwrap();
+        assert_eq!(per_day, vec![(monday.date_naive(), 1), (tuesday.date_naive(), 2)]);
+
+        let per_day = count_learned_per_day(&mut client, user_id, Some("pt")).unwrap();
+        assert_eq!(per_day, vec![(tuesday.date_naive(), 1)]);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
 }
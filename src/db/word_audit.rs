@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error};
+use serde::Serialize;
+use crate::db::words::Word;
+
+#[derive(Serialize)]
+pub struct WordAudit {
+    pub id: i32,
+    pub action: String,
+    pub id_actor: Option<i32>,
+    pub source_id: i32,
+    pub source_text: String,
+    pub source_language: String,
+    pub target_id: i32,
+    pub target_text: String,
+    pub target_language: String,
+    pub affected_rows: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+pub fn insert_word_audit(client: &mut impl GenericClient, action: &str, id_actor: Option<i32>, source: &Word, target: &Word, affected_rows: u64) -> Result<i32, Error> {
+    let affected_rows = affected_rows as i64;
+    let row = client.query_one(
+        "INSERT INTO word_audit
+            (action, id_actor, source_id, source_text, source_language, target_id, target_text, target_language, affected_rows)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+         RETURNING id",
+        &[&action, &id_actor, &source.id, &source.text, &source.language, &target.id, &target.text, &target.language, &affected_rows]
+    )?;
+    Ok(row.get("id"))
+}
+
+pub fn get_word_audit(client: &mut impl GenericClient, limit: i64) -> Result<Vec<WordAudit>, Error> {
+    let rows = client.query(
+        "SELECT id, action, id_actor, source_id, source_text, source_language,
+                target_id, target_text, target_language, affected_rows, created_at
+         FROM word_audit ORDER BY id DESC LIMIT $1",
+        &[&limit]
+    )?;
+    let records = rows.iter().map(|row| WordAudit {
+        id: row.get("id"),
+        action: row.get("action"),
+        id_actor: row.get("id_actor"),
+        source_id: row.get("source_id"),
+        source_text: row.get("source_text"),
+        source_language: row.get("source_language"),
+        target_id: row.get("target_id"),
+        target_text: row.get("target_text"),
+        target_language: row.get("target_language"),
+        affected_rows: row.get("affected_rows"),
+        created_at: row.get("created_at"),
+    }).collect();
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_insert_word_audit() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let source = Word { id: 1, text: "colour".to_string(), language: "en".to_string() };
+        let target = Word { id: 2, text: "color".to_string(), language: "en".to_string() };
+
+        let id = insert_word_audit(&mut client, "merge", None, &source, &target, 3).unwrap();
+
+        let records = get_word_audit(&mut client, 1).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, id);
+        assert_eq!(records[0].action, "merge");
+        assert_eq!(records[0].source_text, "colour");
+        assert_eq!(records[0].target_text, "color");
+        assert_eq!(records[0].affected_rows, 3);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
@@ -0,0 +1,428 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+use crate::db::error::UpdateError;
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct WordList {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner: String,
+    pub is_public: bool,
+    pub total_words: i64,
+    pub known_words: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ListWord {
+    pub word: String,
+    pub language: String,
+    pub known: bool,
+    pub added_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ListProgress {
+    pub known_words: i64,
+    pub total_words: i64,
+    pub percent: f64,
+    pub learned_last_week: i64,
+    pub by_language: Vec<LanguageProgress>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LanguageProgress {
+    pub language: String,
+    pub known_words: i64,
+    pub total_words: i64,
+}
+
+// Known words are counted against `id_viewer`'s vocabulary.
+const LIST_QUERY: &str = "
+    SELECT l.id, l.name, l.description, u.name AS owner, l.is_public, l.created_at,
+           count(e.id_word) AS total_words,
+           count(v.id_word) AS known_words
+    FROM word_lists l
+    JOIN users u ON u.id = l.id_user
+    LEFT JOIN word_list_entries e ON e.id_list = l.id
+    LEFT JOIN vocabulary_user v ON v.id_word = e.id_word AND v.id_user = $1";
+
+fn list_from_row(row: &Row) -> WordList {
+    WordList {
+        id: row.get("id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        owner: row.get("owner"),
+        is_public: row.get("is_public"),
+        total_words: row.get("total_words"),
+        known_words: row.get("known_words"),
+        created_at: row.get("created_at"),
+    }
+}
+
+pub fn create_list(client: &mut impl GenericClient, id_user: i32, name: &str, description: Option<&str>, is_public: bool) -> Result<WordList, UpdateError> {
+    let row = client
+        .query_one(
+            "INSERT INTO word_lists (id_user, name, description, is_public) VALUES ($1, $2, $3, $4) RETURNING id",
+            &[&id_user, &name, &description, &is_public]
+        )
+        .map_err(UpdateError::from_update)?;
+    get_list(client, id_user, row.get("id"))?.ok_or(UpdateError::NotFound)
+}
+
+pub fn get_lists_for_user(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<WordList>, Error> {
+    let rows = client.query(
+        &format!("{} WHERE l.id_user = $1 GROUP BY l.id, u.name ORDER BY l.name", LIST_QUERY),
+        &[&id_user]
+    )?;
+    Ok(rows.iter().map(list_from_row).collect())
+}
+
+pub fn get_public_lists(client: &mut impl GenericClient, id_viewer: i32) -> Result<Vec<WordList>, Error> {
+    let rows = client.query(
+        &format!("{} WHERE l.is_public GROUP BY l.id, u.name ORDER BY l.name, u.name", LIST_QUERY),
+        &[&id_viewer]
+    )?;
+    Ok(rows.iter().map(list_from_row).collect())
+}
+
+// Lists that stopped being public stay subscribed but are hidden until they
+// are published again.
+pub fn get_subscribed_lists(client: &mut impl GenericClient, id_user: i32) -> Result<Vec<WordList>, Error> {
+    let rows = client.query(
+        &format!(
+            "{} JOIN word_list_subscriptions s ON s.id_list = l.id AND s.id_user = $1
+             WHERE l.is_public OR l.id_user = $1
+             GROUP BY l.id, u.name ORDER BY l.name",
+            LIST_QUERY
+        ),
+        &[&id_user]
+    )?;
+    Ok(rows.iter().map(list_from_row).collect())
+}
+
+pub fn get_list(client: &mut impl GenericClient, id_user: i32, id_list: i32) -> Result<Option<WordList>, Error> {
+    let row = client.query_opt(
+        &format!("{} WHERE l.id_user = $1 AND l.id = $2 GROUP BY l.id, u.name", LIST_QUERY),
+        &[&id_user, &id_list]
+    )?;
+    Ok(row.as_ref().map(list_from_row))
+}
+
+// A list is visible to its owner and, once public, to everyone.
+pub fn get_visible_list(client: &mut impl GenericClient, id_viewer: i32, id_list: i32) -> Result<Option<WordList>, Error> {
+    let row = client.query_opt(
+        &format!("{} WHERE l.id = $2 AND (l.id_user = $1 OR l.is_public) GROUP BY l.id, u.name", LIST_QUERY),
+        &[&id_viewer, &id_list]
+    )?;
+    Ok(row.as_ref().map(list_from_row))
+}
+
+pub fn find_list_id(client: &mut impl GenericClient, id_user: i32, name: &str) -> Result<Option<i32>, Error> {
+    let row = client.query_opt(
+        "SELECT id FROM word_lists WHERE id_user = $1 AND name = $2",
+        &[&id_user, &name]
+    )?;
+    Ok(row.map(|row| row.get("id")))
+}
+
+// Fields left as None keep their value; a description of Some(None) clears it.
+pub fn update_list(client: &mut impl GenericClient, id_user: i32, id_list: i32, name: Option<&str>, description: Option<Option<&str>>, is_public: Option<bool>) -> Result<WordList, UpdateError> {
+    let count = client
+        .execute(
+            "UPDATE word_lists SET
+                 name = COALESCE($3, name),
+                 description = CASE WHEN $4 THEN $5 ELSE description END,
+                 is_public = COALESCE($6, is_public)
+             WHERE id_user = $1 AND id = $2",
+            &[&id_user, &id_list, &name, &description.is_some(), &description.flatten(), &is_public]
+        )
+        .map_err(UpdateError::from_update)?;
+    if count == 0 {
+        return Err(UpdateError::NotFound);
+    }
+
+    get_list(client, id_user, id_list)?.ok_or(UpdateError::NotFound)
+}
+
+pub fn delete_list(client: &mut impl GenericClient, id_user: i32, id_list: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM word_lists WHERE id_user = $1 AND id = $2",
+        &[&id_user, &id_list]
+    )?;
+    Ok(count)
+}
+
+pub fn add_words(client: &mut impl GenericClient, id_list: i32, id_words: &[i32]) -> Result<u64, Error> {
+    let count = client.execute(
+        "INSERT INTO word_list_entries (id_list, id_word)
+         SELECT $1, id_word FROM UNNEST($2::int[]) AS id_word
+         ON CONFLICT (id_list, id_word) DO NOTHING",
+        &[&id_list, &id_words]
+    )?;
+    Ok(count)
+}
+
+pub fn add_words_with_dates(client: &mut impl GenericClient, id_list: i32, entries: &[(i32, DateTime<Utc>)]) -> Result<u64, Error> {
+    let id_words: Vec<i32> = entries.iter().map(|(id_word, _)| *id_word).collect();
+    let dates: Vec<DateTime<Utc>> = entries.iter().map(|(_, date)| *date).collect();
+
+    let count = client.execute(
+        "INSERT INTO word_list_entries (id_list, id_word, added_at)
+         SELECT $1, * FROM UNNEST($2::int[], $3::timestamptz[])
+         ON CONFLICT (id_list, id_word) DO NOTHING",
+        &[&id_list, &id_words, &dates]
+    )?;
+    Ok(count)
+}
+
+pub fn remove_words(client: &mut impl GenericClient, id_list: i32, id_words: &[i32]) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM word_list_entries WHERE id_list = $1 AND id_word = ANY($2)",
+        &[&id_list, &id_words]
+    )?;
+    Ok(count)
+}
+
+pub fn get_list_words(client: &mut impl GenericClient, id_viewer: i32, id_list: i32) -> Result<Vec<ListWord>, Error> {
+    let rows = client.query(
+        "SELECT w.text, w.language, v.id_word IS NOT NULL AS known, e.added_at
+         FROM word_list_entries e
+         JOIN words w ON w.id = e.id_word
+         LEFT JOIN vocabulary_user v ON v.id_word = e.id_word AND v.id_user = $1
+         WHERE e.id_list = $2
+         ORDER BY w.language, w.text",
+        &[&id_viewer, &id_list]
+    )?;
+    let words = rows.iter().map(|row| ListWord {
+        word: row.get("text"),
+        language: row.get("language"),
+        known: row.get("known"),
+        added_at: row.get("added_at"),
+    }).collect();
+    Ok(words)
+}
+
+pub fn subscribe(client: &mut impl GenericClient, id_user: i32, id_list: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "INSERT INTO word_list_subscriptions (id_user, id_list)
+         SELECT $1, id FROM word_lists WHERE id = $2 AND (is_public OR id_user = $1)
+         ON CONFLICT (id_user, id_list) DO NOTHING",
+        &[&id_user, &id_list]
+    )?;
+    Ok(count)
+}
+
+pub fn unsubscribe(client: &mut impl GenericClient, id_user: i32, id_list: i32) -> Result<u64, Error> {
+    let count = client.execute(
+        "DELETE FROM word_list_subscriptions WHERE id_user = $1 AND id_list = $2",
+        &[&id_user, &id_list]
+    )?;
+    Ok(count)
+}
+
+pub fn get_list_progress(client: &mut impl GenericClient, id_viewer: i32, id_list: i32) -> Result<ListProgress, Error> {
+    let rows = client.query(
+        "SELECT w.language,
+                count(*) AS total_words,
+                count(v.id_word) AS known_words,
+                count(v.id_word) FILTER (WHERE v.created_at >= now() - INTERVAL '7 days') AS learned_last_week
+         FROM word_list_entries e
+         JOIN words w ON w.id = e.id_word
+         LEFT JOIN vocabulary_user v ON v.id_word = e.id_word AND v.id_user = $1
+         WHERE e.id_list = $2
+         GROUP BY w.language
+         ORDER BY w.language",
+        &[&id_viewer, &id_list]
+    )?;
+
+    let by_language: Vec<LanguageProgress> = rows.iter().map(|row| LanguageProgress {
+        language: row.get("language"),
+        known_words: row.get("known_words"),
+        total_words: row.get("total_words"),
+    }).collect();
+    let known_words: i64 = by_language.iter().map(|language| language.known_words).sum();
+    let total_words: i64 = by_language.iter().map(|language| language.total_words).sum();
+    let learned_last_week: i64 = rows.iter().map(|row| row.get::<_, i64>("learned_last_week")).sum();
+
+    let percent = if total_words == 0 {
+        0.0
+    } else {
+        (known_words as f64 * 1000.0 / total_words as f64).round() / 10.0
+    };
+
+    Ok(ListProgress {
+        known_words,
+        total_words,
+        percent,
+        learned_last_week,
+        by_language,
+    })
+}
+
+// Names of the visible lists the user subscribes to that contain each of
+// `words`.
+pub fn get_subscribed_memberships(client: &mut impl GenericClient, id_user: i32, language: &str, words: &[String]) -> Result<HashMap<String, Vec<String>>, Error> {
+    if words.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = client.query(
+        "SELECT w.text, l.name
+         FROM word_list_subscriptions s
+         JOIN word_lists l ON l.id = s.id_list
+         JOIN word_list_entries e ON e.id_list = l.id
+         JOIN words w ON w.id = e.id_word
+         WHERE s.id_user = $1 AND (l.is_public OR l.id_user = $1)
+           AND w.language = $2 AND w.text = ANY($3)
+         ORDER BY w.text, l.name",
+        &[&id_user, &language, &words]
+    )?;
+
+    let mut memberships: HashMap<String, Vec<String>> = HashMap::new();
+    for row in rows {
+        memberships.entry(row.get("text")).or_default().push(row.get("name"));
+    }
+    Ok(memberships)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::insert_vocab_user;
+    use crate::db::words::{insert_word, merge_words};
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_list_stats() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let list = create_list(&mut client, user_id, "Chapter 3", None, false).unwrap();
+        assert_eq!(list.total_words, 0);
+
+        let known_id = insert_word(&mut client, "unitlist1", "en").unwrap();
+        let unknown_id = insert_word(&mut client, "unitlist2", "de").unwrap();
+        insert_vocab_user(&mut client, user_id, known_id).unwrap();
+
+        assert_eq!(add_words(&mut client, list.id, &[known_id, unknown_id, known_id]).unwrap(), 2);
+
+        let list = get_list(&mut client, user_id, list.id).unwrap().unwrap();
+        assert_eq!((list.known_words, list.total_words), (1, 2));
+
+        let words = get_list_words(&mut client, user_id, list.id).unwrap();
+        let statuses: Vec<(&str, bool)> = words.iter().map(|word| (word.word.as_str(), word.known)).collect();
+        assert_eq!(statuses, vec![("unitlist2", false), ("unitlist1", true)]);
+
+        assert_eq!(remove_words(&mut client, list.id, &[unknown_id]).unwrap(), 1);
+        assert_eq!(get_lists_for_user(&mut client, user_id).unwrap()[0].total_words, 1);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_list_names() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let other_id = insert_user(&mut client, "otheruser").unwrap();
+        let verbs = create_list(&mut client, user_id, "verbs", None, false).unwrap();
+        let business = create_list(&mut client, user_id, "business", None, false).unwrap();
+        create_list(&mut client, other_id, "verbs", None, false).unwrap();
+
+        client.batch_execute("SAVEPOINT duplicate").unwrap();
+        assert!(matches!(create_list(&mut client, user_id, "verbs", None, false), Err(UpdateError::Conflict)));
+        client.batch_execute("ROLLBACK TO SAVEPOINT duplicate").unwrap();
+
+        let renamed = update_list(&mut client, user_id, business.id, Some("work"), Some(Some("office words")), None).unwrap();
+        assert_eq!(renamed.name, "work");
+        assert_eq!(find_list_id(&mut client, user_id, "verbs").unwrap(), Some(verbs.id));
+        assert!(matches!(update_list(&mut client, other_id, verbs.id, Some("mine"), None, None), Err(UpdateError::NotFound)));
+
+        let renamed = update_list(&mut client, user_id, business.id, Some("office"), None, None).unwrap();
+        assert_eq!(renamed.description.as_deref(), Some("office words"));
+        let cleared = update_list(&mut client, user_id, business.id, None, Some(None), None).unwrap();
+        assert_eq!(cleared.name, "office");
+        assert_eq!(cleared.description, None);
+
+        assert_eq!(delete_list(&mut client, other_id, verbs.id).unwrap(), 0);
+        assert_eq!(delete_list(&mut client, user_id, verbs.id).unwrap(), 1);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_merge_keeps_list_entries() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let list = create_list(&mut transaction, user_id, "verbs", None, false).unwrap();
+        let from_id = insert_word(&mut transaction, "unitlistfrom", "en").unwrap();
+        let into_id = insert_word(&mut transaction, "unitlistinto", "en").unwrap();
+        add_words(&mut transaction, list.id, &[from_id]).unwrap();
+
+        merge_words(&mut transaction, from_id, into_id, None).unwrap();
+
+        let words = get_list_words(&mut transaction, user_id, list.id).unwrap();
+        assert_eq!(words.len(), 1);
+        assert_eq!(words[0].word, "unitlistinto");
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_public_lists_and_subscriptions() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let teacher_id = insert_user(&mut client, "teacher").unwrap();
+        let student_id = insert_user(&mut client, "student").unwrap();
+        let list = create_list(&mut client, teacher_id, "CEFR A2 Spanish", None, false).unwrap();
+        let casa_id = insert_word(&mut client, "unitcasa", "es").unwrap();
+        let perro_id = insert_word(&mut client, "unitperro", "es").unwrap();
+        add_words(&mut client, list.id, &[casa_id, perro_id]).unwrap();
+        insert_vocab_user(&mut client, student_id, casa_id).unwrap();
+
+        assert!(get_visible_list(&mut client, student_id, list.id).unwrap().is_none());
+        assert_eq!(subscribe(&mut client, student_id, list.id).unwrap(), 0);
+
+        update_list(&mut client, teacher_id, list.id, None, None, Some(true)).unwrap();
+        let public = get_visible_list(&mut client, student_id, list.id).unwrap().unwrap();
+        assert_eq!(public.owner, "teacher");
+        assert_eq!((public.known_words, public.total_words), (1, 2));
+        assert!(get_public_lists(&mut client, student_id).unwrap().iter().any(|public| public.id == list.id));
+
+        assert_eq!(subscribe(&mut client, student_id, list.id).unwrap(), 1);
+        assert_eq!(get_subscribed_lists(&mut client, student_id).unwrap().len(), 1);
+
+        let progress = get_list_progress(&mut client, student_id, list.id).unwrap();
+        assert_eq!((progress.known_words, progress.total_words, progress.percent), (1, 2, 50.0));
+        assert_eq!(progress.learned_last_week, 1);
+
+        let words = vec!["unitperro".to_string(), "unitgato".to_string()];
+        let memberships = get_subscribed_memberships(&mut client, student_id, "es", &words).unwrap();
+        assert_eq!(memberships["unitperro"], vec!["CEFR A2 Spanish"]);
+        assert!(!memberships.contains_key("unitgato"));
+
+        update_list(&mut client, teacher_id, list.id, None, None, Some(false)).unwrap();
+        assert!(get_subscribed_lists(&mut client, student_id).unwrap().is_empty());
+        assert!(get_subscribed_memberships(&mut client, student_id, "es", &words).unwrap().is_empty());
+
+        assert_eq!(unsubscribe(&mut client, student_id, list.id).unwrap(), 1);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
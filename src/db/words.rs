@@ -1,7 +1,29 @@
-use postgres::{Client, NoTls, Error};
-use std::collections::HashSet;
+use postgres::{GenericClient, Error, Row};
+use std::collections::{HashMap, HashSet};
+use crate::db::error::UpdateError;
+use crate::db::{history, word_audit};
+
+pub struct Word {
+    pub id: i32,
+    pub text: String,
+    pub language: String,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum OnConflict {
+    Fail,
+    Merge,
+}
+
+fn word_from_row(row: &Row) -> Word {
+    Word {
+        id: row.get("id"),
+        text: row.get("text"),
+        language: row.get("language"),
+    }
+}
 
-pub fn insert_word(client: &mut Client, text: &str, language: &str) -> Result<i32, Error> {
+pub fn insert_word(client: &mut impl GenericClient, text: &str, language: &str) -> Result<i32, Error> {
     let insert = client.query_opt("INSERT INTO words(text, language) VALUES ($1, $2)
                                 ON CONFLICT (text, language) DO NOTHING RETURNING id", &[&text, &language])?;
     
@@ -16,7 +38,7 @@ pub fn insert_word(client: &mut Client, text: &str, language: &str) -> Result<i3
     }
 }
 
-pub fn insert_multiple_words(client: &mut Client, words: &[String], language: &str) -> Result<Vec<i32>, Error> {
+pub fn insert_multiple_words(client: &mut impl GenericClient, words: &[String], language: &str) -> Result<Vec<i32>, Error> {
     let existing_words = get_existing_words(client, words, language)?;
 
     let new_words: Vec<&String> = words.iter().filter(|w| !existing_words.contains(w.as_str())).collect();
@@ -39,7 +61,7 @@ pub fn insert_multiple_words(client: &mut Client, words: &[String], language: &s
     Ok(inserted_ids)
 }
 
-fn get_existing_words(client: &mut Client, words: &[String], language: &str) -> Result<HashSet<String>, Error> {
+fn get_existing_words(client: &mut impl GenericClient, words: &[String], language: &str) -> Result<HashSet<String>, Error> {
     let texts: Vec<&str> = words.iter().map(|s| s.as_str()).collect();
     let query = "SELECT text FROM words WHERE text = ANY($1) AND language = $2";
     let rows = client.query(query, &[&texts, &language])?;
@@ -53,46 +75,163 @@ fn get_existing_words(client: &mut Client, words: &[String], language: &str) ->
     Ok(existing)
 }
 
-pub fn get_id_word(client: &mut Client, text: &str, language: &str) -> Result<Option<i32>, Error> {
+pub fn get_id_word(client: &mut impl GenericClient, text: &str, language: &str) -> Result<Option<i32>, Error> {
     let row = client.query_opt("SELECT id FROM words WHERE text = $1 AND language = $2", &[&text, &language])?;
 
     Ok(row.map(|r| r.get("id")))
 }
 
-pub fn get_id_words(client: &mut Client, words: &[String], language: &str) -> Result<Vec<Option<i32>>, Error> {
+pub fn get_id_words(client: &mut impl GenericClient, words: &[String], language: &str) -> Result<Vec<Option<i32>>, Error> {
     let texts: Vec<&str> = words.iter().map(|s| s.as_str()).collect();
 
     let query = "SELECT text, id FROM words WHERE text = ANY($1) AND language = $2";
     let rows = client.query(query, &[&texts, &language])?;
 
-    let mut result = vec![None; words.len()];
-    for row in rows {
-        let text: &str = row.get("text");
-        let id: i32 = row.get("id");
-        if let Some(pos) = words.iter().position(|w| w == text) {
-            result[pos] = Some(id);
-        }
-    }
+    let ids: HashMap<String, i32> = rows.iter().map(|row| (row.get("text"), row.get("id"))).collect();
 
-    Ok(result)
+    Ok(words.iter().map(|word| ids.get(word).copied()).collect())
 }
 
-pub fn get_text_word(client: &mut Client, id: i32) -> Result<Option<String>, Error> {
+pub fn get_text_word(client: &mut impl GenericClient, id: i32) -> Result<Option<String>, Error> {
     let row = client.query_opt("SELECT text FROM words WHERE id = $1", &[&id])?;
     Ok(row.map(|r| r.get("text")))
 }
 
-pub fn update_word_text(client: &mut Client, id: i32, new_text: &str) -> Result<Option<i32>, Error> {
-    let row = client.query_opt("UPDATE words SET text = $1 WHERE id = $2", &[&new_text, &id])?;
-    Ok(row.map(|r| r.get("id")))
+pub fn get_word(client: &mut impl GenericClient, id: i32) -> Result<Option<Word>, Error> {
+    let row = client.query_opt("SELECT id, text, language FROM words WHERE id = $1", &[&id])?;
+    Ok(row.as_ref().map(word_from_row))
 }
 
-pub fn update_word_language(client: &mut Client, id: i32, new_language: &str) -> Result<Option<i32>, Error> {
-    let row = client.query_opt("UPDATE words SET language = $1 WHERE id = $2", &[&new_language, &id])?;
-    Ok(row.map(|r| r.get("id")))
+pub fn update_word_text(client: &mut impl GenericClient, id: i32, new_text: &str, on_conflict: OnConflict) -> Result<Word, UpdateError> {
+    let word = get_word(client, id)?.ok_or(UpdateError::NotFound)?;
+    update_word(client, id, new_text, &word.language, on_conflict)
+}
+
+pub fn update_word_language(client: &mut impl GenericClient, id: i32, new_language: &str, on_conflict: OnConflict) -> Result<Word, UpdateError> {
+    let word = get_word(client, id)?.ok_or(UpdateError::NotFound)?;
+    update_word(client, id, &word.text, new_language, on_conflict)
+}
+
+fn update_word(client: &mut impl GenericClient, id: i32, text: &str, language: &str, on_conflict: OnConflict) -> Result<Word, UpdateError> {
+    if let Some(existing_id) = get_id_word(client, text, language)?.filter(|&existing_id| existing_id != id) {
+        return match on_conflict {
+            OnConflict::Fail => Err(UpdateError::Conflict),
+            OnConflict::Merge => merge(client, id, existing_id, None, false),
+        };
+    }
+
+    let row = client
+        .query_opt(
+            "UPDATE words SET text = $1, language = $2 WHERE id = $3 RETURNING id, text, language",
+            &[&text, &language, &id]
+        )
+        .map_err(UpdateError::from_update)?
+        .ok_or(UpdateError::NotFound)?;
+
+    Ok(word_from_row(&row))
+}
+
+// Merging a word into itself or into a word of another language is a
+// conflict.
+pub fn merge_words(client: &mut impl GenericClient, from_id: i32, into_id: i32, id_actor: Option<i32>) -> Result<Word, UpdateError> {
+    merge(client, from_id, into_id, id_actor, true)
+}
+
+// Changing a word's language merges it into a word of the new language, so
+// only direct merges require both to share one.
+fn merge(client: &mut impl GenericClient, from_id: i32, into_id: i32, id_actor: Option<i32>, same_language: bool) -> Result<Word, UpdateError> {
+    if from_id == into_id {
+        return Err(UpdateError::Conflict);
+    }
+
+    let mut transaction = client.transaction()?;
+
+    let source = get_word(&mut transaction, from_id)?.ok_or(UpdateError::NotFound)?;
+    let target = get_word(&mut transaction, into_id)?.ok_or(UpdateError::NotFound)?;
+    if same_language && source.language != target.language {
+        return Err(UpdateError::Conflict);
+    }
+
+    let moved = transaction.execute(
+        "INSERT INTO vocabulary_user (id_user, id_word, created_at)
+         SELECT id_user, $2, created_at FROM vocabulary_user WHERE id_word = $1
+         ON CONFLICT (id_user, id_word) DO NOTHING",
+        &[&from_id, &into_id]
+    )?;
+    transaction.execute("DELETE FROM vocabulary_user WHERE id_word = $1", &[&from_id])?;
+    transaction.execute("UPDATE vocabulary_events SET id_word = $2 WHERE id_word = $1", &[&from_id, &into_id])?;
+    transaction.execute(
+        "INSERT INTO word_list_entries (id_list, id_word, added_at)
+         SELECT id_list, $2, added_at FROM word_list_entries WHERE id_word = $1
+         ON CONFLICT (id_list, id_word) DO NOTHING",
+        &[&from_id, &into_id]
+    )?;
+    // A user who annotated both words keeps one annotation with the text of
+    // both and every tag.
+    transaction.execute(
+        "UPDATE word_annotations t SET
+             translation = CASE
+                 WHEN s.translation IS NULL OR s.translation = t.translation THEN t.translation
+                 WHEN t.translation IS NULL THEN s.translation
+                 ELSE t.translation || '; ' || s.translation
+             END,
+             note = CASE
+                 WHEN s.note IS NULL OR s.note = t.note THEN t.note
+                 WHEN t.note IS NULL THEN s.note
+                 ELSE t.note || E'\\n\\n' || s.note
+             END,
+             tags = ARRAY(SELECT DISTINCT tag FROM unnest(t.tags || s.tags) AS tag ORDER BY tag),
+             created_at = LEAST(t.created_at, s.created_at),
+             updated_at = now()
+         FROM word_annotations s
+         WHERE s.id_word = $1 AND t.id_word = $2 AND t.id_user = s.id_user",
+        &[&from_id, &into_id]
+    )?;
+    transaction.execute(
+        "UPDATE word_annotations a SET id_word = $2 WHERE a.id_word = $1
+         AND NOT EXISTS (SELECT 1 FROM word_annotations t WHERE t.id_user = a.id_user AND t.id_word = $2)",
+        &[&from_id, &into_id]
+    )?;
+    transaction.execute("DELETE FROM words WHERE id = $1", &[&from_id])?;
+    history::sync_events(&mut transaction, into_id)?;
+
+    word_audit::insert_word_audit(&mut transaction, "merge", id_actor, &source, &target, moved)?;
+
+    transaction.commit()?;
+
+    Ok(target)
+}
+
+pub fn split_word(client: &mut impl GenericClient, lemma_id: i32, form_text: &str, id_actor: Option<i32>) -> Result<Word, UpdateError> {
+    let mut transaction = client.transaction()?;
+
+    let lemma = get_word(&mut transaction, lemma_id)?.ok_or(UpdateError::NotFound)?;
+
+    let row = transaction
+        .query_opt(
+            "INSERT INTO words (text, language) VALUES ($1, $2)
+             ON CONFLICT (text, language) DO NOTHING
+             RETURNING id, text, language",
+            &[&form_text, &lemma.language]
+        )?
+        .ok_or(UpdateError::Conflict)?;
+    let form = word_from_row(&row);
+
+    let copied = transaction.execute(
+        "INSERT INTO vocabulary_user (id_user, id_word, created_at)
+         SELECT id_user, $2, created_at FROM vocabulary_user WHERE id_word = $1",
+        &[&lemma_id, &form.id]
+    )?;
+    history::sync_events(&mut transaction, form.id)?;
+
+    word_audit::insert_word_audit(&mut transaction, "split", id_actor, &lemma, &form, copied)?;
+
+    transaction.commit()?;
+
+    Ok(form)
 }
 
-pub fn delete_word(client: &mut Client, id: i32) -> Result<u64, Error> {
+pub fn delete_word(client: &mut impl GenericClient, id: i32) -> Result<u64, Error> {
     let count = client.execute("DELETE FROM words WHERE id = $1", &[&id])?;
     Ok(count)
 }
@@ -100,7 +239,10 @@ pub fn delete_word(client: &mut Client, id: i32) -> Result<u64, Error> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use postgres::{Client, NoTls};
     use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::{get_users_for_word, insert_vocab_user};
 
     fn connect_test_client() -> Client {
         let db_url = env::var("DATABASE_URL").unwrap();
@@ -112,7 +254,7 @@ mod tests {
         let mut client = connect_test_client();
         client.batch_execute("BEGIN").unwrap();
 
-        let id = insert_word(&mut client, "unitcreate", "english").unwrap();
+        let id = insert_word(&mut client, "unitcreate", "en").unwrap();
         assert!(id > 0);
 
         client.batch_execute("ROLLBACK").unwrap();
@@ -125,19 +267,19 @@ mod tests {
 
         let words = vec!["new1".to_string(), "new2".to_string(), "exist1".to_string()];
 
-        insert_word(&mut client, "exist1", "english").unwrap();
+        insert_word(&mut client, "exist1", "en").unwrap();
 
-        let inserted_ids = insert_multiple_words(&mut client, &words, "english").unwrap();
+        let inserted_ids = insert_multiple_words(&mut client, &words, "en").unwrap();
 
         assert_eq!(inserted_ids.len(), 2);
         assert!(inserted_ids.iter().all(|&id| id > 0));
 
-        let id_new1 = get_id_word(&mut client, "new1", "english").unwrap();
-        let id_new2 = get_id_word(&mut client, "new2", "english").unwrap();
+        let id_new1 = get_id_word(&mut client, "new1", "en").unwrap();
+        let id_new2 = get_id_word(&mut client, "new2", "en").unwrap();
         assert!(inserted_ids.contains(&id_new1.unwrap()));
         assert!(inserted_ids.contains(&id_new2.unwrap()));
 
-        let id_exist1 = get_id_word(&mut client, "exist1", "english").unwrap();
+        let id_exist1 = get_id_word(&mut client, "exist1", "en").unwrap();
         assert!(!inserted_ids.contains(&id_exist1.unwrap()));
 
         client.batch_execute("ROLLBACK").unwrap();
@@ -150,10 +292,10 @@ mod tests {
 
         let words = vec!["exist1".to_string(), "exist2".to_string(), "notexist".to_string()];
         
-        insert_word(&mut client, &words[0], "english").unwrap();
-        insert_word(&mut client, &words[1], "english").unwrap();
+        insert_word(&mut client, &words[0], "en").unwrap();
+        insert_word(&mut client, &words[1], "en").unwrap();
 
-        let existing = get_existing_words(&mut client, &words, "english").unwrap();
+        let existing = get_existing_words(&mut client, &words, "en").unwrap();
 
         let expected: HashSet<String> = vec!["exist1".to_string(), "exist2".to_string()].into_iter().collect();
         assert_eq!(existing, expected);
@@ -168,8 +310,8 @@ mod tests {
 
         let word = "unitread";
 
-        let id = insert_word(&mut client, &word, "english").unwrap();
-        let fetched = get_id_word(&mut client, &word, "english").unwrap();
+        let id = insert_word(&mut client, word, "en").unwrap();
+        let fetched = get_id_word(&mut client, word, "en").unwrap();
         assert_eq!(fetched, Some(id));
 
         client.batch_execute("ROLLBACK").unwrap();
@@ -182,12 +324,10 @@ mod tests {
 
         let words = vec!["test1".to_string(), "test2".to_string(), "test3".to_string()];
 
-        let ids = vec![
-            insert_word(&mut client, &words[0], "english").unwrap(),
-            insert_word(&mut client, &words[1], "english").unwrap(),
-        ];
+        let ids = [insert_word(&mut client, &words[0], "en").unwrap(),
+            insert_word(&mut client, &words[1], "en").unwrap()];
 
-        let fetched = get_id_words(&mut client, &words, "english").unwrap();
+        let fetched = get_id_words(&mut client, &words, "en").unwrap();
 
         assert_eq!(fetched, vec![Some(ids[0]), Some(ids[1]), None]);
 
@@ -199,7 +339,7 @@ mod tests {
         let mut client = connect_test_client();
         client.batch_execute("BEGIN").unwrap();
 
-        let id = insert_word(&mut client, "unitread", "english").unwrap();
+        let id = insert_word(&mut client, "unitread", "en").unwrap();
         let fetched = get_text_word(&mut client, id).unwrap();
         assert_eq!(fetched, Some("unitread".to_string()));
 
@@ -212,11 +352,13 @@ mod tests {
         client.batch_execute("BEGIN").unwrap();
 
         let old_text = "unitup".to_string();
-        let language = "english".to_string();
+        let language = "en".to_string();
         let new_text = "updated".to_string();
 
         let id = insert_word(&mut client, &old_text, &language).unwrap();
-        update_word_text(&mut client, id, &new_text).unwrap();
+        let updated = update_word_text(&mut client, id, &new_text, OnConflict::Fail).unwrap();
+        assert_eq!(updated.id, id);
+        assert_eq!(updated.text, new_text);
 
         let updated_id = get_id_word(&mut client, &new_text, &language).unwrap();
         assert_eq!(updated_id, Some(id));
@@ -224,17 +366,57 @@ mod tests {
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_update_word_text_conflict() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let id = insert_word(&mut client, "unitup", "en").unwrap();
+        insert_word(&mut client, "updated", "en").unwrap();
+
+        let result = update_word_text(&mut client, id, "updated", OnConflict::Fail);
+        assert!(matches!(result, Err(UpdateError::Conflict)));
+
+        let missing = update_word_text(&mut client, -1, "updated", OnConflict::Fail);
+        assert!(matches!(missing, Err(UpdateError::NotFound)));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_update_word_text_merge() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let id = insert_word(&mut transaction, "unitup", "en").unwrap();
+        let existing_id = insert_word(&mut transaction, "updated", "en").unwrap();
+        insert_vocab_user(&mut transaction, user_id, id).unwrap();
+
+        let merged = update_word_text(&mut transaction, id, "updated", OnConflict::Merge).unwrap();
+        assert_eq!(merged.id, existing_id);
+
+        let deleted = get_word(&mut transaction, id).unwrap();
+        assert!(deleted.is_none());
+
+        let users = get_users_for_word(&mut transaction, existing_id).unwrap();
+        assert_eq!(users, vec![user_id]);
+
+        transaction.rollback().unwrap();
+    }
+
     #[test]
     fn test_update_word_language() {
         let mut client = connect_test_client();
         client.batch_execute("BEGIN").unwrap();
 
         let text = "unitup".to_string();
-        let old_language = "english".to_string();
-        let new_language = "português".to_string();
+        let old_language = "en".to_string();
+        let new_language = "pt".to_string();
 
         let id = insert_word(&mut client, &text, &old_language).unwrap();
-        update_word_language(&mut client, id, &new_language).unwrap();
+        let updated = update_word_language(&mut client, id, &new_language, OnConflict::Fail).unwrap();
+        assert_eq!(updated.language, new_language);
 
         let updated_id = get_id_word(&mut client, &text, &new_language).unwrap();
         assert_eq!(updated_id, Some(id));
@@ -242,13 +424,103 @@ mod tests {
         client.batch_execute("ROLLBACK").unwrap();
     }
 
+    #[test]
+    fn test_merge_words() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id1 = insert_user(&mut transaction, "user1").unwrap();
+        let user_id2 = insert_user(&mut transaction, "user2").unwrap();
+        let from_id = insert_word(&mut transaction, "colour", "en").unwrap();
+        let into_id = insert_word(&mut transaction, "color", "en").unwrap();
+        insert_vocab_user(&mut transaction, user_id1, from_id).unwrap();
+        insert_vocab_user(&mut transaction, user_id2, from_id).unwrap();
+        insert_vocab_user(&mut transaction, user_id2, into_id).unwrap();
+
+        let merged = merge_words(&mut transaction, from_id, into_id, Some(user_id1)).unwrap();
+        assert_eq!(merged.id, into_id);
+        assert_eq!(merged.text, "color");
+
+        assert!(get_word(&mut transaction, from_id).unwrap().is_none());
+
+        let mut users = get_users_for_word(&mut transaction, into_id).unwrap();
+        users.sort();
+        assert_eq!(users, vec![user_id1, user_id2]);
+
+        let audit = word_audit::get_word_audit(&mut transaction, 1).unwrap();
+        assert_eq!(audit[0].action, "merge");
+        assert_eq!(audit[0].source_id, from_id);
+        assert_eq!(audit[0].target_id, into_id);
+        assert_eq!(audit[0].affected_rows, 1);
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_merge_words_not_found() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let into_id = insert_word(&mut transaction, "color", "en").unwrap();
+
+        let result = merge_words(&mut transaction, -1, into_id, None);
+        assert!(matches!(result, Err(UpdateError::NotFound)));
+
+        let result = merge_words(&mut transaction, into_id, into_id, None);
+        assert!(matches!(result, Err(UpdateError::Conflict)));
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_merge_words_across_languages() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let from_id = insert_word(&mut transaction, "unitmergegift", "en").unwrap();
+        let into_id = insert_word(&mut transaction, "unitmergegift", "de").unwrap();
+
+        let result = merge_words(&mut transaction, from_id, into_id, None);
+        assert!(matches!(result, Err(UpdateError::Conflict)));
+        assert!(get_word(&mut transaction, from_id).unwrap().is_some());
+
+        transaction.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_split_word() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let lemma_id = insert_word(&mut transaction, "run", "en").unwrap();
+        insert_vocab_user(&mut transaction, user_id, lemma_id).unwrap();
+
+        let form = split_word(&mut transaction, lemma_id, "ran", None).unwrap();
+        assert_eq!(form.text, "ran");
+        assert_eq!(form.language, "en");
+
+        let users = get_users_for_word(&mut transaction, form.id).unwrap();
+        assert_eq!(users, vec![user_id]);
+
+        let audit = word_audit::get_word_audit(&mut transaction, 1).unwrap();
+        assert_eq!(audit[0].action, "split");
+        assert_eq!(audit[0].source_id, lemma_id);
+        assert_eq!(audit[0].target_id, form.id);
+
+        let duplicate = split_word(&mut transaction, lemma_id, "ran", None);
+        assert!(matches!(duplicate, Err(UpdateError::Conflict)));
+
+        transaction.rollback().unwrap();
+    }
+
     #[test]
     fn test_delete_word() {
         let mut client = connect_test_client();
         client.batch_execute("BEGIN").unwrap();
 
         let text = "unitup".to_string();
-        let language = "english".to_string();
+        let language = "en".to_string();
 
         let id = insert_word(&mut client, &text, &language).unwrap();
         let count = delete_word(&mut client, id).unwrap();
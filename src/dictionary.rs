@@ -0,0 +1,447 @@
+use flate2::read::GzDecoder;
+use regex::Regex;
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{BufRead, Read};
+use std::sync::LazyLock;
+
+// Limit on each decompressed file, so a small upload cannot expand into a
+// gzip bomb.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 512 * 1024 * 1024;
+
+const MAX_GLOSS_LENGTH: usize = 80;
+const TEXT_FIELD_TYPES: [u8; 5] = [b'm', b'l', b'g', b'x', b'h'];
+const MARKUP_FIELD_TYPES: [u8; 3] = [b'g', b'x', b'h'];
+
+static LINE_BREAK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<br\s*/?>").unwrap());
+static MARKUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DictionaryFormat {
+    StarDict,
+    Wiktionary,
+}
+
+impl DictionaryFormat {
+    pub fn parse(format: &str) -> Option<DictionaryFormat> {
+        match format {
+            "stardict" => Some(DictionaryFormat::StarDict),
+            "wiktionary" => Some(DictionaryFormat::Wiktionary),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DictionaryFormat::StarDict => "stardict",
+            DictionaryFormat::Wiktionary => "wiktionary",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct DictionaryEntry {
+    pub word: String,
+    pub part_of_speech: Option<String>,
+    pub gloss: String,
+}
+
+#[derive(Debug)]
+pub enum DictionaryError {
+    Io(std::io::Error),
+    Json { line: usize, error: serde_json::Error },
+    Invalid(String),
+    TooLarge,
+}
+
+impl From<std::io::Error> for DictionaryError {
+    fn from(err: std::io::Error) -> DictionaryError {
+        DictionaryError::Io(err)
+    }
+}
+
+impl fmt::Display for DictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DictionaryError::Io(err) => write!(f, "cannot read dictionary: {}", err),
+            DictionaryError::Json { line, error } => write!(f, "invalid JSON on line {}: {}", line, error),
+            DictionaryError::Invalid(message) => write!(f, "invalid StarDict dictionary: {}", message),
+            DictionaryError::TooLarge => write!(f, "dictionary file exceeds the {} MB limit once decompressed", MAX_DECOMPRESSED_SIZE / 1024 / 1024),
+        }
+    }
+}
+
+impl std::error::Error for DictionaryError {}
+
+// Files are told apart by extension: StarDict needs the .ifo, .idx (or
+// .idx.gz) and .dict (or .dict.dz) files, Wiktionary extracts may be
+// gzip-compressed.
+pub fn parse_files(format: DictionaryFormat, files: &[(String, Vec<u8>)], language: &str) -> Result<Vec<DictionaryEntry>, DictionaryError> {
+    let find = |suffix: &str| files.iter().find(|(name, _)| name.to_lowercase().ends_with(suffix)).map(|(_, bytes)| bytes.as_slice());
+
+    match format {
+        DictionaryFormat::StarDict => {
+            let ifo = find(".ifo").ok_or_else(|| DictionaryError::Invalid("missing .ifo file".to_string()))?;
+            let idx = match (find(".idx"), find(".idx.gz")) {
+                (Some(idx), _) => Cow::Borrowed(idx),
+                (None, Some(compressed)) => Cow::Owned(decompress_dictzip(compressed)?),
+                (None, None) => return Err(DictionaryError::Invalid("missing .idx file".to_string())),
+            };
+            let dict = match (find(".dict"), find(".dict.dz")) {
+                (Some(dict), _) => Cow::Borrowed(dict),
+                (None, Some(compressed)) => Cow::Owned(decompress_dictzip(compressed)?),
+                (None, None) => return Err(DictionaryError::Invalid("missing .dict file".to_string())),
+            };
+            parse_stardict(&String::from_utf8_lossy(ifo), &idx, &dict)
+        }
+        DictionaryFormat::Wiktionary => {
+            let mut entries = Vec::new();
+            for (name, bytes) in files {
+                let content = if name.to_lowercase().ends_with(".gz") {
+                    Cow::Owned(decompress_dictzip(bytes)?)
+                } else {
+                    Cow::Borrowed(bytes.as_slice())
+                };
+                entries.extend(parse_wiktionary(content.as_ref(), language)?);
+            }
+            Ok(entries)
+        }
+    }
+}
+
+pub fn normalize_headword(word: &str) -> String {
+    word.trim().replace('’', "'").to_lowercase()
+}
+
+// Glosses shown next to words in an analysis: the first line of the
+// definition, cut at a word boundary.
+pub fn short_gloss(gloss: &str) -> String {
+    let line = gloss.lines().map(str::trim).find(|line| !line.is_empty()).unwrap_or("");
+    if line.chars().count() <= MAX_GLOSS_LENGTH {
+        return line.to_string();
+    }
+
+    let cut: String = line.chars().take(MAX_GLOSS_LENGTH).collect();
+    let cut = match cut.rfind(' ') {
+        Some(index) if index > MAX_GLOSS_LENGTH / 2 => &cut[..index],
+        _ => cut.as_str(),
+    };
+    format!("{}…", cut.trim_end_matches([' ', ',', ';', ':']))
+}
+
+// A StarDict dictionary is three files: the .ifo metadata, the .idx index of
+// (word, offset, size) records and the .dict data, often gzip-compressed as
+// .dict.dz.
+pub fn parse_stardict(ifo: &str, idx: &[u8], dict: &[u8]) -> Result<Vec<DictionaryEntry>, DictionaryError> {
+    let mut lines = ifo.trim_start_matches('\u{feff}').lines();
+    if lines.next().map(str::trim) != Some("StarDict's dict ifo file") {
+        return Err(DictionaryError::Invalid("missing .ifo header".to_string()));
+    }
+    let info: HashMap<&str, &str> = lines
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .collect();
+
+    let offset_size = match info.get("idxoffsetbits").copied() {
+        None | Some("32") => 4,
+        Some("64") => 8,
+        Some(bits) => return Err(DictionaryError::Invalid(format!("unsupported idxoffsetbits: {}", bits))),
+    };
+    let type_sequence = info.get("sametypesequence").map(|sequence| sequence.as_bytes());
+
+    let mut entries = Vec::new();
+    let mut position = 0;
+    while position < idx.len() {
+        let end = idx[position..]
+            .iter()
+            .position(|&byte| byte == 0)
+            .map(|index| position + index)
+            .ok_or_else(|| DictionaryError::Invalid("truncated index".to_string()))?;
+        let word = String::from_utf8_lossy(&idx[position..end]).into_owned();
+        position = end + 1;
+
+        let offset = read_number(idx, &mut position, offset_size)?;
+        let size = read_number(idx, &mut position, 4)?;
+        let data = offset
+            .checked_add(size)
+            .and_then(|end| dict.get(offset..end))
+            .ok_or_else(|| DictionaryError::Invalid(format!("definition of '{}' is out of bounds", word)))?;
+
+        let gloss = definition_text(data, type_sequence);
+        if !gloss.is_empty() && !word.trim().is_empty() {
+            entries.push(DictionaryEntry {
+                word: normalize_headword(&word),
+                part_of_speech: None,
+                gloss,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn decompress_dictzip(bytes: &[u8]) -> Result<Vec<u8>, DictionaryError> {
+    decompress_with_limit(bytes, MAX_DECOMPRESSED_SIZE)
+}
+
+fn decompress_with_limit(bytes: &[u8], limit: u64) -> Result<Vec<u8>, DictionaryError> {
+    let mut data = Vec::new();
+    GzDecoder::new(bytes).take(limit + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > limit {
+        return Err(DictionaryError::TooLarge);
+    }
+    Ok(data)
+}
+
+fn read_number(bytes: &[u8], position: &mut usize, size: usize) -> Result<usize, DictionaryError> {
+    let field = bytes
+        .get(*position..*position + size)
+        .ok_or_else(|| DictionaryError::Invalid("truncated index".to_string()))?;
+    *position += size;
+    Ok(field.iter().fold(0, |number, &byte| (number << 8) | byte as usize))
+}
+
+// Each definition is a list of typed fields. Lower-case types are text
+// (terminated by a NUL unless they are the last field of a sametypesequence),
+// upper-case types are binary data prefixed by their size.
+fn definition_text(data: &[u8], type_sequence: Option<&[u8]>) -> String {
+    let mut fields = Vec::new();
+    let mut position = 0;
+    let mut index = 0;
+
+    while position < data.len() {
+        let field_type = match type_sequence {
+            Some(sequence) => match sequence.get(index) {
+                Some(&field_type) => field_type,
+                None => break,
+            },
+            None => {
+                position += 1;
+                data[position - 1]
+            }
+        };
+        let last = type_sequence.is_some_and(|sequence| index + 1 == sequence.len());
+        index += 1;
+
+        let content = if field_type.is_ascii_uppercase() {
+            let size = if last {
+                data.len() - position
+            } else {
+                let Ok(size) = read_number(data, &mut position, 4) else {
+                    break;
+                };
+                size
+            };
+            let end = (position + size).min(data.len());
+            let content = &data[position..end];
+            position = end;
+            content
+        } else {
+            let end = if last {
+                data.len()
+            } else {
+                data[position..].iter().position(|&byte| byte == 0).map_or(data.len(), |end| position + end)
+            };
+            let content = &data[position..end];
+            position = end + 1;
+            content
+        };
+
+        if !TEXT_FIELD_TYPES.contains(&field_type) {
+            continue;
+        }
+        let text = String::from_utf8_lossy(content);
+        let text = if MARKUP_FIELD_TYPES.contains(&field_type) {
+            decode_entities(&MARKUP.replace_all(&LINE_BREAK.replace_all(&text, "\n"), ""))
+        } else {
+            text.into_owned()
+        };
+        let text = text.trim();
+        if !text.is_empty() {
+            fields.push(text.to_string());
+        }
+    }
+
+    fields.join("\n")
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+}
+
+#[derive(Deserialize)]
+struct WiktionaryWord {
+    word: String,
+    lang_code: Option<String>,
+    pos: Option<String>,
+    #[serde(default)]
+    senses: Vec<WiktionarySense>,
+}
+
+#[derive(Deserialize)]
+struct WiktionarySense {
+    #[serde(default)]
+    glosses: Vec<String>,
+}
+
+// Wiktionary extracts (wiktextract, kaikki.org) are JSON Lines with one word
+// per line. Dumps may mix languages, so only `language` entries are kept.
+// Nested senses repeat their parent glosses first; the last one is the most
+// specific.
+pub fn parse_wiktionary(reader: impl BufRead, language: &str) -> Result<Vec<DictionaryEntry>, DictionaryError> {
+    let mut entries = Vec::new();
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let word: WiktionaryWord = serde_json::from_str(&line)
+            .map_err(|error| DictionaryError::Json { line: index + 1, error })?;
+        if word.lang_code.as_deref().is_some_and(|code| code != language) {
+            continue;
+        }
+
+        for sense in word.senses {
+            let Some(gloss) = sense.glosses.last().map(|gloss| gloss.trim()).filter(|gloss| !gloss.is_empty()) else {
+                continue;
+            };
+            entries.push(DictionaryEntry {
+                word: normalize_headword(&word.word),
+                part_of_speech: word.pos.clone(),
+                gloss: gloss.to_string(),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::GzEncoder;
+    use std::io::Write;
+
+    fn stardict_index(entries: &[(&str, usize, usize)]) -> Vec<u8> {
+        let mut idx = Vec::new();
+        for (word, offset, size) in entries {
+            idx.extend_from_slice(word.as_bytes());
+            idx.push(0);
+            idx.extend_from_slice(&(*offset as u32).to_be_bytes());
+            idx.extend_from_slice(&(*size as u32).to_be_bytes());
+        }
+        idx
+    }
+
+    #[test]
+    fn test_parse_stardict_same_type_sequence() {
+        let ifo = "StarDict's dict ifo file\nversion=2.4.2\nbookname=Test\nwordcount=2\nsametypesequence=h\n";
+        let dict = b"<b>house</b><br>home &amp; hearth<i>dog</i>";
+        let idx = stardict_index(&[("Haus", 0, 33), ("Hund", 33, 10)]);
+
+        let entries = parse_stardict(ifo, &idx, dict).unwrap();
+        assert_eq!(entries, vec![
+            DictionaryEntry { word: "haus".to_string(), part_of_speech: None, gloss: "house\nhome & hearth".to_string() },
+            DictionaryEntry { word: "hund".to_string(), part_of_speech: None, gloss: "dog".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_stardict_typed_fields() {
+        let ifo = "StarDict's dict ifo file\nversion=3.0.0\nbookname=Test\n";
+        let mut dict = b"t[kat]\0mcat\0".to_vec();
+        dict.extend_from_slice(b"P");
+        dict.extend_from_slice(&3u32.to_be_bytes());
+        dict.extend_from_slice(b"png");
+        let idx = stardict_index(&[("gato", 0, dict.len())]);
+
+        let entries = parse_stardict(ifo, &idx, &dict).unwrap();
+        assert_eq!(entries[0].gloss, "cat");
+    }
+
+    #[test]
+    fn test_parse_stardict_invalid() {
+        assert!(parse_stardict("not a dictionary", &[], &[]).is_err());
+
+        let ifo = "StarDict's dict ifo file\nsametypesequence=m\n";
+        let idx = stardict_index(&[("gato", 0, 100)]);
+        assert!(parse_stardict(ifo, &idx, b"cat").is_err());
+
+        let ifo = "StarDict's dict ifo file\nidxoffsetbits=64\nsametypesequence=m\n";
+        let mut idx = b"gato\0".to_vec();
+        idx.extend_from_slice(&u64::MAX.to_be_bytes());
+        idx.extend_from_slice(&1u32.to_be_bytes());
+        assert!(parse_stardict(ifo, &idx, b"cat").is_err());
+    }
+
+    #[test]
+    fn test_parse_files() {
+        let ifo = b"StarDict's dict ifo file\nsametypesequence=m\n".to_vec();
+        let idx = stardict_index(&[("gato", 0, 3)]);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"cat").unwrap();
+        let dict = encoder.finish().unwrap();
+
+        let files = vec![
+            ("es-en.ifo".to_string(), ifo.clone()),
+            ("es-en.idx".to_string(), idx),
+            ("es-en.dict.dz".to_string(), dict),
+        ];
+        let entries = parse_files(DictionaryFormat::StarDict, &files, "es").unwrap();
+        assert_eq!(entries[0].gloss, "cat");
+
+        let error = parse_files(DictionaryFormat::StarDict, &files[..1], "es").unwrap_err();
+        assert!(error.to_string().contains(".idx"));
+    }
+
+    #[test]
+    fn test_decompress_dictzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"house").unwrap();
+        let compressed = encoder.finish().unwrap();
+
+        assert_eq!(decompress_dictzip(&compressed).unwrap(), b"house");
+        assert_eq!(decompress_with_limit(&compressed, 5).unwrap(), b"house");
+        assert!(matches!(decompress_with_limit(&compressed, 4), Err(DictionaryError::TooLarge)));
+    }
+
+    #[test]
+    fn test_parse_wiktionary() {
+        let dump = concat!(
+            r#"{"word": "Haus", "lang_code": "de", "pos": "noun", "senses": [{"glosses": ["house"]}, {"glosses": ["building", "household"]}]}"#, "\n",
+            "\n",
+            r#"{"word": "house", "lang_code": "en", "pos": "noun", "senses": [{"glosses": ["a building"]}]}"#, "\n",
+            r#"{"word": "Hund", "lang_code": "de", "pos": "noun", "senses": [{"tags": ["no-gloss"]}]}"#, "\n",
+        );
+
+        let entries = parse_wiktionary(dump.as_bytes(), "de").unwrap();
+        assert_eq!(entries, vec![
+            DictionaryEntry { word: "haus".to_string(), part_of_speech: Some("noun".to_string()), gloss: "house".to_string() },
+            DictionaryEntry { word: "haus".to_string(), part_of_speech: Some("noun".to_string()), gloss: "household".to_string() },
+        ]);
+
+        let error = parse_wiktionary("{\"word\": \"a\"}\nnot json\n".as_bytes(), "de").unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn test_short_gloss() {
+        assert_eq!(short_gloss("\n house \nhome"), "house");
+
+        let long = "a building for human habitation, especially one that is lived in by a family or small group of people";
+        let short = short_gloss(long);
+        assert!(short.ends_with('…'));
+        assert!(short.chars().count() <= MAX_GLOSS_LENGTH + 1);
+        assert!(long.starts_with(short.trim_end_matches('…')));
+    }
+}
@@ -0,0 +1,99 @@
+use serde::Serialize;
+
+const SAMPLE_SIZE: usize = 4096;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub enum Encoding {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "latin-1")]
+    Latin1,
+}
+
+pub fn detect(bytes: &[u8]) -> Encoding {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Encoding::Utf8;
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Encoding::Utf16Le;
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Encoding::Utf16Be;
+    }
+
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+    let pairs = sample.len() / 2;
+    if pairs > 0 {
+        let even_zeros = sample.iter().step_by(2).filter(|byte| **byte == 0).count();
+        let odd_zeros = sample.iter().skip(1).step_by(2).filter(|byte| **byte == 0).count();
+        if odd_zeros * 3 > pairs && even_zeros * 10 < pairs {
+            return Encoding::Utf16Le;
+        }
+        if even_zeros * 3 > pairs && odd_zeros * 10 < pairs {
+            return Encoding::Utf16Be;
+        }
+    }
+
+    if std::str::from_utf8(bytes).is_ok() {
+        Encoding::Utf8
+    } else {
+        Encoding::Latin1
+    }
+}
+
+pub fn decode(bytes: &[u8]) -> (String, Encoding) {
+    let encoding = detect(bytes);
+
+    let text = match encoding {
+        Encoding::Utf8 => String::from_utf8_lossy(bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes)).into_owned(),
+        Encoding::Utf16Le => decode_utf16(bytes.strip_prefix(&[0xFF, 0xFE]).unwrap_or(bytes), u16::from_le_bytes),
+        Encoding::Utf16Be => decode_utf16(bytes.strip_prefix(&[0xFE, 0xFF]).unwrap_or(bytes), u16::from_be_bytes),
+        Encoding::Latin1 => bytes.iter().map(|byte| *byte as char).collect(),
+    };
+
+    (text, encoding)
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str, little_endian: bool) -> Vec<u8> {
+        text.encode_utf16()
+            .flat_map(|unit| if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_utf8() {
+        assert_eq!(decode("Olá, mundo".as_bytes()), ("Olá, mundo".to_string(), Encoding::Utf8));
+        assert_eq!(decode(b"\xEF\xBB\xBFcat"), ("cat".to_string(), Encoding::Utf8));
+    }
+
+    #[test]
+    fn test_decode_utf16() {
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16("Olá", true));
+        assert_eq!(decode(&bytes), ("Olá".to_string(), Encoding::Utf16Le));
+
+        assert_eq!(decode(&utf16("The cat sat", true)), ("The cat sat".to_string(), Encoding::Utf16Le));
+        assert_eq!(decode(&utf16("The cat sat", false)), ("The cat sat".to_string(), Encoding::Utf16Be));
+    }
+
+    #[test]
+    fn test_decode_latin1() {
+        assert_eq!(decode(b"Ol\xE1, cora\xE7\xE3o"), ("Olá, coração".to_string(), Encoding::Latin1));
+    }
+}
@@ -0,0 +1,317 @@
+use quick_xml::escape::unescape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::reader::Reader;
+use quick_xml::XmlVersion;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Cursor, Read};
+use zip::ZipArchive;
+use crate::html::html_to_text;
+
+// Bounds the decompressed size of all entries read from one book, so a small
+// archive cannot expand into more memory than the upload limit allows for.
+pub const MAX_DECOMPRESSED_SIZE: u64 = 256 * 1024 * 1024;
+
+pub struct Chapter {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+pub struct Book {
+    pub title: Option<String>,
+    pub chapters: Vec<Chapter>,
+}
+
+#[derive(Debug)]
+pub enum EpubError {
+    Zip(zip::result::ZipError),
+    Xml(quick_xml::Error),
+    Io(std::io::Error),
+    MissingRootfile,
+    TooLarge,
+}
+
+impl From<zip::result::ZipError> for EpubError {
+    fn from(err: zip::result::ZipError) -> EpubError {
+        EpubError::Zip(err)
+    }
+}
+
+impl From<quick_xml::Error> for EpubError {
+    fn from(err: quick_xml::Error) -> EpubError {
+        EpubError::Xml(err)
+    }
+}
+
+impl From<std::io::Error> for EpubError {
+    fn from(err: std::io::Error) -> EpubError {
+        EpubError::Io(err)
+    }
+}
+
+impl fmt::Display for EpubError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EpubError::Zip(err) => write!(f, "invalid EPUB archive: {}", err),
+            EpubError::Xml(err) => write!(f, "invalid EPUB package: {}", err),
+            EpubError::Io(err) => write!(f, "cannot read EPUB entry: {}", err),
+            EpubError::MissingRootfile => write!(f, "EPUB container does not declare a package file"),
+            EpubError::TooLarge => write!(f, "EPUB content exceeds the {} MB limit once decompressed", MAX_DECOMPRESSED_SIZE / 1024 / 1024),
+        }
+    }
+}
+
+impl std::error::Error for EpubError {}
+
+struct Package {
+    title: Option<String>,
+    manifest: HashMap<String, String>,
+    spine: Vec<String>,
+}
+
+pub fn parse_epub(bytes: &[u8]) -> Result<Book, EpubError> {
+    parse_epub_with_limit(bytes, MAX_DECOMPRESSED_SIZE)
+}
+
+fn parse_epub_with_limit(bytes: &[u8], limit: u64) -> Result<Book, EpubError> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes))?;
+    let mut remaining = limit;
+
+    let container = read_entry(&mut archive, "META-INF/container.xml", &mut remaining)?;
+    let package_path = find_rootfile(&container)?.ok_or(EpubError::MissingRootfile)?;
+    let base = match package_path.rfind('/') {
+        Some(index) => &package_path[..=index],
+        None => "",
+    };
+
+    let package = parse_package(&read_entry(&mut archive, &package_path, &mut remaining)?)?;
+
+    let mut chapters = Vec::new();
+    for idref in &package.spine {
+        let Some(href) = package.manifest.get(idref) else {
+            continue;
+        };
+        let path = resolve_path(base, href);
+        let xhtml = match read_entry(&mut archive, &path, &mut remaining) {
+            Ok(xhtml) => xhtml,
+            Err(EpubError::TooLarge) => return Err(EpubError::TooLarge),
+            Err(_) => continue,
+        };
+
+        let document = html_to_text(&xhtml);
+        if document.text.is_empty() {
+            continue;
+        }
+        chapters.push(Chapter {
+            title: document.title,
+            text: document.text,
+        });
+    }
+
+    Ok(Book {
+        title: package.title,
+        chapters,
+    })
+}
+
+// Reads at most `remaining` bytes, which are then taken from the budget.
+fn read_entry(archive: &mut ZipArchive<Cursor<&[u8]>>, path: &str, remaining: &mut u64) -> Result<String, EpubError> {
+    let entry = archive.by_name(path)?;
+    let mut content = String::new();
+    entry.take(*remaining + 1).read_to_string(&mut content)?;
+    if content.len() as u64 > *remaining {
+        return Err(EpubError::TooLarge);
+    }
+    *remaining -= content.len() as u64;
+    Ok(content)
+}
+
+fn find_rootfile(container: &str) -> Result<Option<String>, EpubError> {
+    let mut reader = Reader::from_str(container);
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element) if element.local_name().as_ref() == "rootfile" => {
+                if let Some(path) = attribute(&element, "full-path")? {
+                    return Ok(Some(path));
+                }
+            }
+            Event::Eof => return Ok(None),
+            _ => {}
+        }
+    }
+}
+
+fn parse_package(opf: &str) -> Result<Package, EpubError> {
+    let mut reader = Reader::from_str(opf);
+    let mut package = Package {
+        title: None,
+        manifest: HashMap::new(),
+        spine: Vec::new(),
+    };
+    let mut in_title = false;
+    let mut title = String::new();
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) | Event::Empty(element) => match element.local_name().as_ref() {
+                "title" if package.title.is_none() => in_title = true,
+                "item" => {
+                    if let (Some(id), Some(href)) = (attribute(&element, "id")?, attribute(&element, "href")?) {
+                        package.manifest.insert(id, href);
+                    }
+                }
+                "itemref" => {
+                    if let Some(idref) = attribute(&element, "idref")? {
+                        package.spine.push(idref);
+                    }
+                }
+                _ => {}
+            },
+            Event::Text(text) if in_title => title.push_str(&text.xml10_content()),
+            Event::GeneralRef(reference) if in_title => {
+                let entity = format!("&{};", reference.xml10_content());
+                title.push_str(&unescape(&entity).unwrap_or_default());
+            }
+            Event::End(element) if in_title && element.local_name().as_ref() == "title" => {
+                in_title = false;
+                let trimmed = title.trim();
+                if !trimmed.is_empty() {
+                    package.title = Some(trimmed.to_string());
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(package)
+}
+
+fn attribute(element: &BytesStart, name: &str) -> Result<Option<String>, EpubError> {
+    let Some(attribute) = element.try_get_attribute(name).map_err(quick_xml::Error::from)? else {
+        return Ok(None);
+    };
+    Ok(Some(attribute.normalized_value(XmlVersion::Implicit1_0)?.into_owned()))
+}
+
+fn resolve_path(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or(href);
+    let mut parts: Vec<&str> = base.split('/').filter(|part| !part.is_empty()).collect();
+
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    percent_decode(&parts.join("/"))
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = path.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    fn build_epub(files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_parse_epub() {
+        let container = r#"<?xml version="1.0"?>
+            <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+              <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+            </container>"#;
+        let opf = r#"<?xml version="1.0"?>
+            <package xmlns="http://www.idpf.org/2007/opf" xmlns:dc="http://purl.org/dc/elements/1.1/">
+              <metadata><dc:title>Tom &amp; Jerry</dc:title></metadata>
+              <manifest>
+                <item id="c1" href="text/chapter%201.xhtml" media-type="application/xhtml+xml"/>
+                <item id="c2" href="text/chapter2.xhtml" media-type="application/xhtml+xml"/>
+                <item id="empty" href="text/cover.xhtml" media-type="application/xhtml+xml"/>
+              </manifest>
+              <spine><itemref idref="empty"/><itemref idref="c2"/><itemref idref="c1"/></spine>
+            </package>"#;
+        let chapter1 = "<html><head><title>One</title></head><body><p>The cat sat.</p></body></html>";
+        let chapter2 = "<html><body><h1>Two</h1><p>The dog ran.</p></body></html>";
+        let cover = "<html><body><img src=\"cover.jpg\"/></body></html>";
+
+        let bytes = build_epub(&[
+            ("mimetype", "application/epub+zip"),
+            ("META-INF/container.xml", container),
+            ("OEBPS/content.opf", opf),
+            ("OEBPS/text/chapter 1.xhtml", chapter1),
+            ("OEBPS/text/chapter2.xhtml", chapter2),
+            ("OEBPS/text/cover.xhtml", cover),
+        ]);
+
+        let book = parse_epub(&bytes).unwrap();
+        assert_eq!(book.title.as_deref(), Some("Tom & Jerry"));
+        assert_eq!(book.chapters.len(), 2);
+        assert_eq!(book.chapters[0].title.as_deref(), Some("Two"));
+        assert_eq!(book.chapters[0].text, "Two\n\nThe dog ran.");
+        assert_eq!(book.chapters[1].title.as_deref(), Some("One"));
+        assert_eq!(book.chapters[1].text, "The cat sat.");
+    }
+
+    #[test]
+    fn test_parse_epub_rejects_oversized_entries() {
+        let container = r#"<container><rootfiles><rootfile full-path="content.opf"/></rootfiles></container>"#;
+        let opf = r#"<package><manifest><item id="c1" href="c1.xhtml"/></manifest><spine><itemref idref="c1"/></spine></package>"#;
+        let chapter = format!("<html><body><p>{}</p></body></html>", "a".repeat(2 * 1024 * 1024));
+
+        let bytes = build_epub(&[
+            ("META-INF/container.xml", container),
+            ("content.opf", opf),
+            ("c1.xhtml", &chapter),
+        ]);
+        assert!(bytes.len() < 64 * 1024);
+
+        assert!(matches!(parse_epub_with_limit(&bytes, 1024 * 1024), Err(EpubError::TooLarge)));
+        assert_eq!(parse_epub_with_limit(&bytes, 4 * 1024 * 1024).unwrap().chapters.len(), 1);
+    }
+
+    #[test]
+    fn test_parse_epub_rejects_non_zip() {
+        assert!(matches!(parse_epub(b"not a zip"), Err(EpubError::Zip(_))));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        assert_eq!(resolve_path("OEBPS/", "text/a.xhtml#part"), "OEBPS/text/a.xhtml");
+        assert_eq!(resolve_path("OEBPS/text/", "../b%20c.xhtml"), "OEBPS/b c.xhtml");
+        assert_eq!(resolve_path("", "a.xhtml"), "a.xhtml");
+    }
+}
@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use postgres::{GenericClient, Error};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use crate::db::{texts, vocab_user, word_lists};
+use crate::tokenizer::{split_sentences, tokenize};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+    Anki,
+}
+
+impl ExportFormat {
+    pub fn parse(format: &str) -> Option<ExportFormat> {
+        match format {
+            "csv" => Some(ExportFormat::Csv),
+            "json" => Some(ExportFormat::Json),
+            "anki" => Some(ExportFormat::Anki),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Json => "application/json",
+            ExportFormat::Anki => "text/tab-separated-values; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+            ExportFormat::Anki => "txt",
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ExportEntry {
+    pub word: String,
+    pub language: String,
+    pub status: String,
+    pub added_at: Option<DateTime<Utc>>,
+    pub example: Option<String>,
+}
+
+pub fn collect_entries(client: &mut impl GenericClient, id_user: i32, language: Option<&str>, status: Option<&str>, id_list: Option<i32>) -> Result<Vec<ExportEntry>, Error> {
+    let vocabulary = vocab_user::get_vocabulary_for_user(client, id_user, language)?;
+    let user_texts = texts::get_texts_for_user(client, id_user, language)?;
+
+    let mut examples: HashMap<(String, String), String> = HashMap::new();
+    let mut seen_words: Vec<(String, String)> = Vec::new();
+    for text in &user_texts {
+        for sentence in split_sentences(&text.content) {
+            for word in tokenize(sentence) {
+                let key = (word, text.language.clone());
+                if !examples.contains_key(&key) {
+                    examples.insert(key.clone(), sentence.to_string());
+                    seen_words.push(key);
+                }
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+
+    if status.is_none_or(|status| status == "known") {
+        for entry in &vocabulary {
+            let key = (entry.word.clone(), entry.language.clone());
+            entries.push(ExportEntry {
+                word: entry.word.clone(),
+                language: entry.language.clone(),
+                status: "known".to_string(),
+                added_at: entry.created_at,
+                example: examples.get(&key).cloned(),
+            });
+        }
+    }
+
+    if status.is_none_or(|status| status == "unknown") {
+        let known: HashSet<(&str, &str)> = vocabulary
+            .iter()
+            .map(|entry| (entry.word.as_str(), entry.language.as_str()))
+            .collect();
+
+        for (word, language) in &seen_words {
+            if known.contains(&(word.as_str(), language.as_str())) {
+                continue;
+            }
+            entries.push(ExportEntry {
+                word: word.clone(),
+                language: language.clone(),
+                status: "unknown".to_string(),
+                added_at: None,
+                example: examples.get(&(word.clone(), language.clone())).cloned(),
+            });
+        }
+    }
+
+    let Some(id_list) = id_list else {
+        return Ok(entries);
+    };
+
+    // A list narrows the export to its words, and its unknown words are
+    // exported even when they never appeared in a stored text.
+    let list_words: Vec<word_lists::ListWord> = word_lists::get_list_words(client, id_user, id_list)?
+        .into_iter()
+        .filter(|entry| language.is_none_or(|language| entry.language == language))
+        .collect();
+    let members: HashSet<(&str, &str)> = list_words
+        .iter()
+        .map(|entry| (entry.word.as_str(), entry.language.as_str()))
+        .collect();
+    entries.retain(|entry| members.contains(&(entry.word.as_str(), entry.language.as_str())));
+
+    if status.is_none_or(|status| status == "unknown") {
+        let exported: HashSet<(String, String)> = entries
+            .iter()
+            .map(|entry| (entry.word.clone(), entry.language.clone()))
+            .collect();
+        for entry in list_words.iter().filter(|entry| !entry.known) {
+            let key = (entry.word.clone(), entry.language.clone());
+            if exported.contains(&key) {
+                continue;
+            }
+            entries.push(ExportEntry {
+                word: entry.word.clone(),
+                language: entry.language.clone(),
+                status: "unknown".to_string(),
+                added_at: None,
+                example: examples.get(&key).cloned(),
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+pub fn render(entries: &[ExportEntry], format: ExportFormat) -> String {
+    match format {
+        ExportFormat::Csv => render_csv(entries),
+        ExportFormat::Json => serde_json::to_string_pretty(entries).unwrap(),
+        ExportFormat::Anki => render_anki(entries),
+    }
+}
+
+fn render_csv(entries: &[ExportEntry]) -> String {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["word", "language", "status", "added_at", "example"]).unwrap();
+
+    for entry in entries {
+        let added_at = entry.added_at.map(|date| date.to_rfc3339()).unwrap_or_default();
+        writer.write_record([
+            entry.word.as_str(),
+            entry.language.as_str(),
+            entry.status.as_str(),
+            added_at.as_str(),
+            entry.example.as_deref().unwrap_or(""),
+        ]).unwrap();
+    }
+
+    String::from_utf8(writer.into_inner().unwrap()).unwrap()
+}
+
+fn render_anki(entries: &[ExportEntry]) -> String {
+    let mut deck = String::from("#separator:tab\n#html:false\n#tags column:3\n");
+
+    for entry in entries {
+        let example = entry.example.as_deref().unwrap_or("");
+        let tags = format!("{} {}", entry.language.replace(' ', "_"), entry.status);
+        deck.push_str(&format!("{}\t{}\t{}\n", anki_field(&entry.word), anki_field(example), tags));
+    }
+
+    deck
+}
+
+fn anki_field(value: &str) -> String {
+    value.replace(['\t', '\n', '\r'], " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::insert_vocab_user;
+    use crate::db::words::insert_word;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    fn sample_entries() -> Vec<ExportEntry> {
+        vec![
+            ExportEntry {
+                word: "cat".to_string(),
+                language: "en".to_string(),
+                status: "known".to_string(),
+                added_at: None,
+                example: Some("The cat sat, quietly.".to_string()),
+            },
+            ExportEntry {
+                word: "mat".to_string(),
+                language: "en".to_string(),
+                status: "unknown".to_string(),
+                added_at: None,
+                example: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_collect_entries() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let word_id = insert_word(&mut client, "cat", "en").unwrap();
+        insert_vocab_user(&mut client, user_id, word_id).unwrap();
+        texts::insert_text(&mut client, user_id, "en", None, "The cat sat. A dog ran.").unwrap();
+
+        let entries = collect_entries(&mut client, user_id, Some("en"), None, None).unwrap();
+        assert_eq!(entries.len(), 6);
+        assert_eq!(entries[0].word, "cat");
+        assert_eq!(entries[0].status, "known");
+        assert!(entries[0].added_at.is_some());
+        assert_eq!(entries[0].example.as_deref(), Some("The cat sat."));
+
+        let unknown = collect_entries(&mut client, user_id, None, Some("unknown"), None).unwrap();
+        let words: Vec<&str> = unknown.iter().map(|entry| entry.word.as_str()).collect();
+        assert_eq!(words, vec!["the", "sat", "a", "dog", "ran"]);
+        assert_eq!(unknown[3].example.as_deref(), Some("A dog ran."));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_collect_entries_for_list() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let cat_id = insert_word(&mut client, "cat", "en").unwrap();
+        let dog_id = insert_word(&mut client, "dog", "en").unwrap();
+        let fox_id = insert_word(&mut client, "fox", "en").unwrap();
+        insert_vocab_user(&mut client, user_id, cat_id).unwrap();
+        texts::insert_text(&mut client, user_id, "en", None, "The cat sat. A dog ran.").unwrap();
+
+        let list = word_lists::create_list(&mut client, user_id, "animals", None, false).unwrap();
+        word_lists::add_words(&mut client, list.id, &[cat_id, dog_id, fox_id]).unwrap();
+
+        let entries = collect_entries(&mut client, user_id, None, None, Some(list.id)).unwrap();
+        let words: Vec<(&str, &str)> = entries.iter().map(|entry| (entry.word.as_str(), entry.status.as_str())).collect();
+        assert_eq!(words, vec![("cat", "known"), ("dog", "unknown"), ("fox", "unknown")]);
+        assert_eq!(entries[1].example.as_deref(), Some("A dog ran."));
+
+        let known = collect_entries(&mut client, user_id, None, Some("known"), Some(list.id)).unwrap();
+        assert_eq!(known.len(), 1);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_render_csv() {
+        let csv = render(&sample_entries(), ExportFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "word,language,status,added_at,example");
+        assert_eq!(lines[1], "cat,en,known,,\"The cat sat, quietly.\"");
+        assert_eq!(lines[2], "mat,en,unknown,,");
+    }
+
+    #[test]
+    fn test_render_anki() {
+        let deck = render(&sample_entries(), ExportFormat::Anki);
+        let lines: Vec<&str> = deck.lines().collect();
+        assert_eq!(lines[0], "#separator:tab");
+        assert_eq!(lines[3], "cat\tThe cat sat, quietly.\ten known");
+        assert_eq!(lines[4], "mat\t\ten unknown");
+    }
+
+    #[test]
+    fn test_render_json() {
+        let json = render(&sample_entries(), ExportFormat::Json);
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["word"], "cat");
+        assert_eq!(parsed[1]["status"], "unknown");
+    }
+}
@@ -0,0 +1,234 @@
+use pulldown_cmark::{html, Parser};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::sync::LazyLock;
+
+const SKIPPED_ELEMENTS: [&str; 6] = ["head", "script", "style", "noscript", "template", "svg"];
+
+const BLOCK_ELEMENTS: [&str; 24] = [
+    "address", "article", "aside", "blockquote", "dd", "div", "dl", "dt",
+    "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "li", "main", "p", "pre", "section", "tr",
+];
+
+const BOILERPLATE_ELEMENTS: [&str; 11] = [
+    "aside", "button", "dialog", "footer", "form", "header", "iframe", "input", "menu", "nav", "select",
+];
+
+const CONTENT_ROOTS: &str = "article, main, [role=main]";
+
+static BOILERPLATE_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)advert|banner|breadcrumb|comment|cookie|footer|menu|newsletter|popup|promo|related|share|sidebar|social|subscribe").unwrap()
+});
+
+pub struct Document {
+    pub title: Option<String>,
+    pub text: String,
+}
+
+pub fn html_to_text(html: &str) -> Document {
+    let document = Html::parse_document(html);
+
+    let title = select_text(&document, "title").or_else(|| select_text(&document, "h1, h2, h3"));
+
+    let body_selector = Selector::parse("body").unwrap();
+    let root = document.select(&body_selector).next().unwrap_or(document.root_element());
+
+    Document {
+        title,
+        text: element_text(root),
+    }
+}
+
+fn select_text(document: &Html, selector: &str) -> Option<String> {
+    let selector = Selector::parse(selector).unwrap();
+    document
+        .select(&selector)
+        .map(|element| collapse_whitespace(&element.text().collect::<String>()))
+        .find(|text| !text.is_empty())
+}
+
+pub fn extract_article(html: &str) -> Document {
+    let document = Html::parse_document(html);
+    let root = main_content(&document);
+
+    let heading = Selector::parse("h1").unwrap();
+    let title = root
+        .select(&heading)
+        .map(|element| collapse_whitespace(&element.text().collect::<String>()))
+        .find(|text| !text.is_empty())
+        .or_else(|| select_text(&document, "title"));
+
+    let mut raw = String::new();
+    collect_text(root, &mut raw, is_boilerplate);
+
+    Document {
+        title,
+        text: paragraphs(&raw),
+    }
+}
+
+pub fn markdown_to_text(markdown: &str) -> Document {
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, Parser::new(markdown));
+    html_to_text(&rendered)
+}
+
+fn main_content(document: &Html) -> ElementRef<'_> {
+    let roots = Selector::parse(CONTENT_ROOTS).unwrap();
+    if let Some(root) = document.select(&roots).max_by_key(|element| paragraph_length(*element)) {
+        return root;
+    }
+
+    let candidates = Selector::parse("div, section, td").unwrap();
+    let best = document
+        .select(&candidates)
+        .filter(|element| !is_boilerplate(*element))
+        .max_by_key(|element| paragraph_length(*element))
+        .filter(|element| paragraph_length(*element) > 0);
+    if let Some(best) = best {
+        return best;
+    }
+
+    let body = Selector::parse("body").unwrap();
+    document.select(&body).next().unwrap_or(document.root_element())
+}
+
+fn paragraph_length(element: ElementRef) -> usize {
+    element
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "p")
+        .map(|child| child.text().map(str::len).sum::<usize>())
+        .sum()
+}
+
+fn is_skipped(element: ElementRef) -> bool {
+    SKIPPED_ELEMENTS.contains(&element.value().name())
+}
+
+fn is_boilerplate(element: ElementRef) -> bool {
+    if is_skipped(element) || BOILERPLATE_ELEMENTS.contains(&element.value().name()) {
+        return true;
+    }
+    if element.value().attr("aria-hidden") == Some("true") {
+        return true;
+    }
+
+    let value = element.value();
+    let label = format!("{} {}", value.attr("class").unwrap_or(""), value.attr("id").unwrap_or(""));
+    BOILERPLATE_PATTERN.is_match(&label)
+}
+
+pub fn element_text(element: ElementRef) -> String {
+    let mut raw = String::new();
+    collect_text(element, &mut raw, is_skipped);
+    paragraphs(&raw)
+}
+
+fn paragraphs(raw: &str) -> String {
+    raw.split("\n\n")
+        .map(|paragraph| {
+            paragraph
+                .lines()
+                .map(collapse_whitespace)
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .filter(|paragraph| !paragraph.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn collect_text(element: ElementRef, output: &mut String, skip: fn(ElementRef) -> bool) {
+    let name = element.value().name();
+
+    let is_block = BLOCK_ELEMENTS.contains(&name);
+    if is_block {
+        output.push_str("\n\n");
+    }
+
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => output.push_str(&text.replace('\n', " ")),
+            Node::Element(child_element) if child_element.name() == "br" => output.push('\n'),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child)
+                    && !skip(child)
+                {
+                    collect_text(child, output, skip);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if is_block {
+        output.push_str("\n\n");
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<html><head><title>Chapter 1</title><style>p { color: red; }</style></head>
+            <body><h1>The  Beginning</h1><p>It was a <em>dark</em>
+            night.</p><p>Line one<br/>line two</p><script>var x = 1;</script></body></html>";
+
+        let document = html_to_text(html);
+        assert_eq!(document.title.as_deref(), Some("Chapter 1"));
+        assert_eq!(document.text, "The Beginning\n\nIt was a dark night.\n\nLine one\nline two");
+    }
+
+    #[test]
+    fn test_html_to_text_title_from_heading() {
+        let document = html_to_text("<body><h2>Prologue</h2><p>Text.</p></body>");
+        assert_eq!(document.title.as_deref(), Some("Prologue"));
+    }
+
+    #[test]
+    fn test_extract_article() {
+        let html = r#"<html><head><title>Cats | Example News</title></head><body>
+            <header><nav><a href="/">Home</a> <a href="/world">World</a></nav></header>
+            <div class="sidebar"><p>Trending now everywhere</p></div>
+            <div id="content">
+              <h1>Why cats sit</h1>
+              <p>Cats like to sit on mats.</p>
+              <div class="share-buttons">Share on Twitter</div>
+              <p>Nobody knows why.</p>
+              <section class="comments"><p>First comment!</p></section>
+            </div>
+            <footer><p>Copyright 2024</p></footer>
+            </body></html>"#;
+
+        let document = extract_article(html);
+        assert_eq!(document.title.as_deref(), Some("Why cats sit"));
+        assert_eq!(document.text, "Why cats sit\n\nCats like to sit on mats.\n\nNobody knows why.");
+    }
+
+    #[test]
+    fn test_extract_article_prefers_article_element() {
+        let html = "<body><div><p>Menu text that is fairly long but not content.</p></div>
+            <article><p>Real content.</p></article></body>";
+
+        let document = extract_article(html);
+        assert_eq!(document.text, "Real content.");
+    }
+
+    #[test]
+    fn test_markdown_to_text() {
+        let markdown = "# Title\n\nSome *emphasis* and a [link](http://example.com).\n\n- one\n- two\n\n```\nlet x = 1;\n```\n";
+
+        let document = markdown_to_text(markdown);
+        assert_eq!(document.title.as_deref(), Some("Title"));
+        assert_eq!(document.text, "Title\n\nSome emphasis and a link.\n\none\n\ntwo\n\nlet x = 1;");
+    }
+}
@@ -0,0 +1,217 @@
+use postgres::{GenericClient, Error};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use crate::db::{vocab_user, words};
+use crate::tokenizer::normalize_word;
+
+const WORD_COLUMNS: [&str; 5] = ["word", "term", "front", "expression", "palavra"];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ImportFormat {
+    Csv,
+    Tsv,
+    Anki,
+    Lingq,
+}
+
+impl ImportFormat {
+    pub fn parse(format: &str) -> Option<ImportFormat> {
+        match format {
+            "csv" => Some(ImportFormat::Csv),
+            "tsv" => Some(ImportFormat::Tsv),
+            "anki" => Some(ImportFormat::Anki),
+            "lingq" => Some(ImportFormat::Lingq),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub new: Vec<String>,
+    pub existing: Vec<String>,
+    pub rejected: Vec<String>,
+}
+
+pub fn parse_entries(content: &str, format: ImportFormat) -> Vec<String> {
+    match format {
+        ImportFormat::Csv | ImportFormat::Lingq => parse_delimited(content, b','),
+        ImportFormat::Tsv => parse_delimited(content, b'\t'),
+        ImportFormat::Anki => parse_anki(content),
+    }
+}
+
+fn parse_delimited(content: &str, delimiter: u8) -> Vec<String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    let mut records = reader.records().filter_map(Result::ok).peekable();
+
+    let mut column = 0;
+    if let Some(header) = records.peek() {
+        let position = header
+            .iter()
+            .position(|field| WORD_COLUMNS.contains(&field.trim().to_lowercase().as_str()));
+        if let Some(position) = position {
+            column = position;
+            records.next();
+        }
+    }
+
+    records
+        .filter_map(|record| record.get(column).map(str::to_string))
+        .collect()
+}
+
+fn parse_anki(content: &str) -> Vec<String> {
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+
+    let mut separator = '\t';
+    let mut entries = Vec::new();
+
+    for line in content.lines() {
+        if let Some(header) = line.strip_prefix('#') {
+            if let Some(value) = header.strip_prefix("separator:") {
+                separator = match value.trim() {
+                    "tab" => '\t',
+                    "comma" => ',',
+                    "semicolon" => ';',
+                    "pipe" => '|',
+                    "space" => ' ',
+                    other => other.chars().next().unwrap_or('\t'),
+                };
+            }
+            continue;
+        }
+
+        let front = line.split(separator).next().unwrap_or("");
+        let front = tags.replace_all(front, "").replace("&nbsp;", " ");
+        entries.push(front);
+    }
+
+    entries
+}
+
+pub fn import_words(client: &mut impl GenericClient, id_user: i32, language: &str, entries: &[String], dry_run: bool) -> Result<ImportReport, Error> {
+    let mut report = ImportReport::default();
+
+    let mut seen = HashSet::new();
+    let mut normalized = Vec::new();
+    for entry in entries {
+        if entry.trim().is_empty() {
+            continue;
+        }
+        match normalize_word(entry) {
+            Some(word) => {
+                if seen.insert(word.clone()) {
+                    normalized.push(word);
+                }
+            }
+            None => report.rejected.push(entry.clone()),
+        }
+    }
+
+    let mut transaction = client.transaction()?;
+
+    if !dry_run {
+        words::insert_multiple_words(&mut transaction, &normalized, language)?;
+    }
+
+    let ids = words::get_id_words(&mut transaction, &normalized, language)?;
+    let known: HashSet<i32> = vocab_user::get_words_for_user(&mut transaction, id_user)?.into_iter().collect();
+
+    let mut new_ids = Vec::new();
+    for (word, id) in normalized.into_iter().zip(ids) {
+        match id {
+            Some(id) if known.contains(&id) => report.existing.push(word),
+            _ => {
+                new_ids.extend(id);
+                report.new.push(word);
+            }
+        }
+    }
+
+    if !dry_run {
+        vocab_user::insert_vocab_users_multiple(&mut transaction, id_user, &new_ids)?;
+        transaction.commit()?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::vocab_user::insert_vocab_user;
+    use crate::db::words::insert_word;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let entries = parse_entries("translation,word\ngato,Cat\n\"cachorro, cão\",dog\n", ImportFormat::Csv);
+        assert_eq!(entries, vec!["Cat", "dog"]);
+
+        let entries = parse_entries("cat,gato\ndog\n", ImportFormat::Csv);
+        assert_eq!(entries, vec!["cat", "dog"]);
+    }
+
+    #[test]
+    fn test_parse_tsv() {
+        let entries = parse_entries("cat\tgato\ndog\tcachorro\n", ImportFormat::Tsv);
+        assert_eq!(entries, vec!["cat", "dog"]);
+    }
+
+    #[test]
+    fn test_parse_lingq() {
+        let entries = parse_entries("Term,Hint,Status\nrun,correr,3\n", ImportFormat::Lingq);
+        assert_eq!(entries, vec!["run"]);
+    }
+
+    #[test]
+    fn test_parse_anki() {
+        let content = "#separator:tab\n#html:true\n<b>cat</b>\tgato\ndog&nbsp;\tcachorro\n";
+        let entries = parse_entries(content, ImportFormat::Anki);
+        assert_eq!(entries, vec!["cat", "dog "]);
+
+        let content = "#separator:semicolon\nbird;pássaro\n";
+        let entries = parse_entries(content, ImportFormat::Anki);
+        assert_eq!(entries, vec!["bird"]);
+    }
+
+    #[test]
+    fn test_import_words() {
+        let mut client = connect_test_client();
+        let mut transaction = client.transaction().unwrap();
+
+        let user_id = insert_user(&mut transaction, "testuser").unwrap();
+        let known_id = insert_word(&mut transaction, "cat", "en").unwrap();
+        insert_vocab_user(&mut transaction, user_id, known_id).unwrap();
+
+        let entries = vec!["Cat".to_string(), "dog".to_string(), "two words".to_string(), "Dog".to_string()];
+
+        let report = import_words(&mut transaction, user_id, "en", &entries, true).unwrap();
+        assert_eq!(report.new, vec!["dog"]);
+        assert_eq!(report.existing, vec!["cat"]);
+        assert_eq!(report.rejected, vec!["two words"]);
+        assert_eq!(words::get_id_word(&mut transaction, "dog", "en").unwrap(), None);
+
+        let report = import_words(&mut transaction, user_id, "en", &entries, false).unwrap();
+        assert_eq!(report.new, vec!["dog"]);
+
+        let known = vocab_user::get_words_for_user(&mut transaction, user_id).unwrap();
+        assert_eq!(known.len(), 2);
+
+        transaction.rollback().unwrap();
+    }
+}
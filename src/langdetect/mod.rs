@@ -0,0 +1,168 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use crate::tokenizer::tokenize;
+
+const PROFILE_SIZE: usize = 300;
+const MAX_NGRAM: usize = 3;
+const MIN_LETTERS: usize = 12;
+const MIN_CONFIDENCE: f64 = 0.02;
+
+const SAMPLES: [(&str, &str, &str); 6] = [
+    ("en", "English", include_str!("samples/en.txt")),
+    ("pt", "Portuguese", include_str!("samples/pt.txt")),
+    ("es", "Spanish", include_str!("samples/es.txt")),
+    ("fr", "French", include_str!("samples/fr.txt")),
+    ("de", "German", include_str!("samples/de.txt")),
+    ("it", "Italian", include_str!("samples/it.txt")),
+];
+
+struct Profile {
+    code: &'static str,
+    name: &'static str,
+    ranks: HashMap<String, usize>,
+}
+
+static PROFILES: LazyLock<Vec<Profile>> = LazyLock::new(|| {
+    SAMPLES
+        .iter()
+        .map(|(code, name, sample)| Profile {
+            code,
+            name,
+            ranks: ranked_ngrams(sample)
+                .into_iter()
+                .enumerate()
+                .map(|(rank, ngram)| (ngram, rank))
+                .collect(),
+        })
+        .collect()
+});
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Detection {
+    pub code: &'static str,
+    pub name: &'static str,
+    pub confidence: f64,
+}
+
+impl Detection {
+    pub fn matches(&self, language: &str) -> bool {
+        language.trim() == self.code
+    }
+}
+
+pub fn detect(text: &str) -> Option<Detection> {
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    if letters < MIN_LETTERS {
+        return None;
+    }
+
+    let ngrams = ranked_ngrams(text);
+
+    let mut distances: Vec<(&Profile, usize)> = PROFILES
+        .iter()
+        .map(|profile| (profile, distance(&ngrams, &profile.ranks)))
+        .collect();
+    distances.sort_by_key(|(_, distance)| *distance);
+
+    let (best, best_distance) = distances[0];
+    let second_distance = distances[1].1;
+    let confidence = (second_distance - best_distance) as f64 / second_distance.max(1) as f64;
+
+    if confidence < MIN_CONFIDENCE {
+        return None;
+    }
+
+    Some(Detection {
+        code: best.code,
+        name: best.name,
+        confidence: (confidence * 100.0).round() / 100.0,
+    })
+}
+
+pub fn resolve_language(stated: Option<&str>, text: &str) -> Result<(String, Option<String>), String> {
+    let detection = detect(text);
+
+    match (stated.map(str::trim).filter(|language| !language.is_empty()), detection) {
+        (Some(language), Some(detection)) if !detection.matches(language) => Ok((
+            language.to_string(),
+            Some(format!("The text looks like {} ({}), not {}", detection.name, detection.code, language)),
+        )),
+        (Some(language), _) => Ok((language.to_string(), None)),
+        (None, Some(detection)) => Ok((detection.code.to_string(), None)),
+        (None, None) => Err("Could not detect the language of the text; please provide it".to_string()),
+    }
+}
+
+fn ranked_ngrams(text: &str) -> Vec<String> {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+
+    for word in tokenize(text) {
+        let padded: Vec<char> = format!("_{}_", word).chars().collect();
+        for size in 1..=MAX_NGRAM {
+            for window in padded.windows(size) {
+                if window == ['_'] {
+                    continue;
+                }
+                *counts.entry(window.iter().collect()).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut ngrams: Vec<(String, u32)> = counts.into_iter().collect();
+    ngrams.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ngrams.truncate(PROFILE_SIZE);
+    ngrams.into_iter().map(|(ngram, _)| ngram).collect()
+}
+
+fn distance(ngrams: &[String], ranks: &HashMap<String, usize>) -> usize {
+    ngrams
+        .iter()
+        .enumerate()
+        .map(|(rank, ngram)| match ranks.get(ngram) {
+            Some(profile_rank) => rank.abs_diff(*profile_rank),
+            None => PROFILE_SIZE,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let cases = [
+            ("en", "I think we should go to the market before it closes tonight."),
+            ("pt", "Eu acho que nós devíamos ir ao mercado antes que ele feche hoje."),
+            ("es", "Creo que deberíamos ir al mercado antes de que cierre esta noche."),
+            ("fr", "Je pense que nous devrions aller au marché avant qu'il ne ferme ce soir."),
+            ("de", "Ich glaube, wir sollten zum Markt gehen, bevor er heute Abend schließt."),
+            ("it", "Penso che dovremmo andare al mercato prima che chiuda stasera."),
+        ];
+
+        for (code, text) in cases {
+            assert_eq!(detect(text).map(|detection| detection.code), Some(code), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_detect_short_text() {
+        assert_eq!(detect("ok"), None);
+    }
+
+    #[test]
+    fn test_resolve_language() {
+        let text = "The weather was lovely and we walked along the river all afternoon.";
+
+        assert_eq!(resolve_language(None, text), Ok(("en".to_string(), None)));
+        assert_eq!(resolve_language(Some("en"), text), Ok(("en".to_string(), None)));
+
+        let (language, warning) = resolve_language(Some("de"), text).unwrap();
+        assert_eq!(language, "de");
+        assert!(warning.unwrap().contains("(en)"));
+
+        assert!(resolve_language(None, "ok").is_err());
+        assert_eq!(resolve_language(Some("en"), "ok"), Ok(("en".to_string(), None)));
+    }
+}
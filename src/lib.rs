@@ -0,0 +1,13 @@
+pub mod backup;
+pub mod coverage;
+pub mod dictionary;
+pub mod db;
+pub mod encoding;
+pub mod epub;
+pub mod export;
+pub mod html;
+pub mod import;
+pub mod langdetect;
+pub mod stats;
+pub mod subtitles;
+pub mod tokenizer;
@@ -1,77 +1,2156 @@
 use axum::{
-    routing::{get, post},
+    extract::{DefaultBodyLimit, Multipart, Path, Query},
+    http::{header::{CONTENT_DISPOSITION, CONTENT_TYPE}, HeaderMap},
+    middleware,
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
     Router,
     Json,
 };
 use serde::Deserialize;
-mod tokenizer;
-use tokenizer::frequency_counter_from_text;
-mod db;
-use db::{vocab_user, words};
+use token_analyser::tokenizer::{frequency_counter_from_text, normalize_word};
+use token_analyser::backup::{self, Backup, BACKUP_VERSION};
+use token_analyser::coverage::{compute_class_report, compute_coverage, rank_texts};
+use token_analyser::epub::parse_epub;
+use token_analyser::{dictionary, encoding, html, langdetect, stats, subtitles};
+use token_analyser::db::error::UpdateError;
+use token_analyser::export::{self, ExportFormat};
+use token_analyser::import::{self, ImportFormat};
+use token_analyser::db::{self, annotations, api_tokens, dictionaries, groups, history, languages, profiles, sessions, texts, users, vocab_user, word_audit, word_lists, words};
 use std::env;
 use postgres::{Client, NoTls};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use serde_json::json;
 use tokio::task;
 use tower_http::cors::{CorsLayer, Any};
+mod auth;
+use auth::{AuthUser, Scope};
+mod cli;
+use clap::Parser;
+
+const MAX_ARCHIVE_SIZE: usize = 64 * 1024 * 1024;
+const MAX_TEXT_SIZE: usize = 16 * 1024 * 1024;
 
 #[tokio::main]
 async fn main() {
+    let cli = cli::Cli::parse();
+
+    if let Some(command) = cli.command {
+        task::spawn_blocking(move || cli::run(command)).await.unwrap();
+        return;
+    }
+
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
         .allow_headers(Any);
 
-    let db_url = std::env::var("DATABASE_URL").unwrap();
+    let db_url = std::env::var("DATABASE_URL").unwrap();
+
+    tokio::task::spawn_blocking(move || {
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        db::init_tables(&mut client).expect("Falha ao criar tabelas");
+    }).await.unwrap();
+
+    let read_routes = Router::new()
+        .route("/users/{id}", get(get_user))
+        .route("/export", get(export_vocabulary))
+        .route("/backup", get(backup_account))
+        .route("/profiles", get(list_profiles))
+        .route("/history", get(vocabulary_history))
+        .route("/dictionaries", get(list_dictionaries))
+        .route("/annotations", get(search_annotations))
+        .route("/annotations/{language}/{word}", get(get_annotation))
+        .route("/lists", get(list_word_lists))
+        .route("/lists/public", get(list_public_word_lists))
+        .route("/lists/subscribed", get(list_subscribed_word_lists))
+        .route("/lists/{id}", get(get_word_list))
+        .route("/lists/{id}/progress", get(word_list_progress))
+        .route("/groups", get(list_groups))
+        .route("/groups/{id}", get(get_group))
+        .route("/groups/{id}/progress", get(group_progress))
+        .route("/groups/{id}/words/{language}/{word}", get(group_word_knowledge))
+        .route("/groups/{id}/texts", get(list_group_texts))
+        .route("/groups/{id}/texts/{text_id}", get(get_group_text))
+        .route("/groups/{id}/texts/{text_id}/report", get(group_text_report))
+        .route("/dictionary/lookup", get(lookup_word))
+        .route("/stats", get(learning_stats))
+        .route("/texts/ranking", get(text_ranking));
+
+    let write_routes = Router::new()
+        // Analysing and uploading store the texts and their new words.
+        .route("/analyze-text", post(analyse_text))
+        .route("/upload/epub", post(upload_epub).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)))
+        .route("/upload/subtitles", post(upload_subtitles).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)))
+        .route("/upload/article", post(upload_article).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)))
+        .route("/upload/text", post(upload_text).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)))
+        .route("/toggle-word", post(toggle_word_knowledge))
+        .route("/toggle-words", post(toggle_words_knowledge))
+        .route("/mark-all-known", post(mark_all_known))
+        .route("/history/undo", post(undo_changes))
+        .route("/history/replay", post(replay_history))
+        .route("/import", post(import_vocabulary))
+        .route("/restore", post(restore_account).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)))
+        .route("/profiles/{language}", put(save_profile).delete(delete_profile))
+        .route("/annotations/{language}/{word}", put(save_annotation).delete(delete_annotation))
+        .route("/lists", post(create_word_list))
+        .route("/lists/{id}", patch(update_word_list).delete(delete_word_list))
+        .route("/lists/{id}/words", post(add_list_words).delete(remove_list_words))
+        .route("/lists/{id}/subscription", post(subscribe_word_list).delete(unsubscribe_word_list))
+        .route("/groups", post(create_group))
+        .route("/groups/{id}", delete(delete_group))
+        .route("/groups/{id}/members", post(add_group_member))
+        .route("/groups/{id}/members/{user_id}", delete(remove_group_member))
+        .route("/groups/{id}/invitation", post(accept_group_invitation).delete(decline_group_invitation))
+        .route("/groups/{id}/texts", post(assign_group_text))
+        .route("/groups/{id}/texts/{text_id}", delete(unassign_group_text))
+        .route_layer(middleware::from_fn(auth::require_write));
+
+    let account_routes = Router::new()
+        .route("/users/{id}", patch(rename_user).delete(delete_user))
+        .route("/api-tokens", get(list_api_tokens).post(create_api_token))
+        .route("/api-tokens/{id}", delete(revoke_api_token))
+        .route_layer(middleware::from_fn(auth::require_session));
+
+    let admin_routes = Router::new()
+        .route("/admin/words/merge", post(merge_words))
+        .route("/admin/words/split", post(split_word))
+        .route("/admin/words/audit", get(word_audit))
+        .route("/admin/dictionaries", post(import_dictionary).layer(DefaultBodyLimit::max(MAX_ARCHIVE_SIZE)))
+        .route("/admin/dictionaries/{id}", delete(delete_dictionary))
+        .route_layer(middleware::from_fn(auth::require_admin))
+        .route_layer(middleware::from_fn(auth::require_session));
+
+    let protected_routes = read_routes
+        .merge(write_routes)
+        .merge(account_routes)
+        .merge(admin_routes)
+        .route_layer(middleware::from_fn(auth::authenticate));
+
+    let app = Router::new()
+        .route("/", get(|| async {"Hello, World!"}))
+        .route("/register", post(register))
+        .route("/users", post(register))
+        .route("/login", post(login))
+        .route("/logout", post(logout))
+        .route("/languages", get(list_languages))
+        .merge(protected_routes)
+        .layer(cors);
+
+    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    axum::serve(listener, app).await.unwrap();
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    name: String,
+    password: String,
+}
+
+async fn register(Json(payload): Json<Credentials>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let name = payload.name.trim();
+
+        if name.is_empty() {
+            return json!({
+                "success": false,
+                "message": "User name must not be empty"
+            });
+        }
+
+        if payload.password.chars().count() < 8 {
+            return json!({
+                "success": false,
+                "message": "Password must have at least 8 characters"
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let password_hash = auth::hash_password(&payload.password);
+
+        match users::insert_user_with_password(&mut client, name, &password_hash).unwrap() {
+            Some(user_id) => json!({
+                "success": true,
+                "user_id": user_id
+            }),
+            None => json!({
+                "success": false,
+                "message": format!("User name '{}' is already taken", name)
+            }),
+        }
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn login(Json(payload): Json<Credentials>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let credentials = users::get_credentials_by_name(&mut client, payload.name.trim()).unwrap();
+
+        let user_id = match credentials {
+            Some((user_id, Some(password_hash))) if auth::verify_password(&payload.password, &password_hash) => user_id,
+            _ => {
+                return json!({
+                    "success": false,
+                    "message": "Invalid user name or password"
+                });
+            }
+        };
+
+        let token = auth::generate_token();
+
+        sessions::delete_expired_sessions(&mut client).unwrap();
+        sessions::insert_session(&mut client, &auth::hash_token(&token), user_id).unwrap();
+
+        json!({
+            "success": true,
+            "user_id": user_id,
+            "token": token
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn logout(headers: HeaderMap) -> Json<serde_json::Value> {
+    let Some(token_hash) = auth::bearer_token(&headers).map(auth::hash_token) else {
+        return Json(json!({
+            "success": false,
+            "message": "Missing bearer token"
+        }));
+    };
+
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let count = sessions::delete_session(&mut client, &token_hash).unwrap();
+
+        json!({ "success": count > 0 })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+fn forbidden_user(id: i32) -> serde_json::Value {
+    json!({
+        "success": false,
+        "message": format!("Not allowed to access user {}", id)
+    })
+}
+
+async fn get_user(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    if id != user_id {
+        return Json(forbidden_user(id));
+    }
+
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        match users::get_user_by_id(&mut client, id).unwrap() {
+            Some(name) => json!({
+                "success": true,
+                "id": id,
+                "name": name
+            }),
+            None => json!({
+                "success": false,
+                "message": format!("User {} not found", id)
+            }),
+        }
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct Rename {
+    name: String,
+}
+
+async fn rename_user(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Json(payload): Json<Rename>) -> Json<serde_json::Value> {
+    if id != user_id {
+        return Json(forbidden_user(id));
+    }
+
+    let response_json = task::spawn_blocking(move || {
+        let name = payload.name.trim();
+
+        if name.is_empty() {
+            return json!({
+                "success": false,
+                "message": "User name must not be empty"
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        match users::update_user_name(&mut client, id, name) {
+            Ok(user) => json!({
+                "success": true,
+                "id": user.id,
+                "name": user.name
+            }),
+            Err(UpdateError::Conflict) => json!({
+                "success": false,
+                "message": format!("User name '{}' is already taken", name)
+            }),
+            Err(UpdateError::NotFound) => json!({
+                "success": false,
+                "message": format!("User {} not found", id)
+            }),
+            Err(UpdateError::Db(err)) => panic!("{}", err),
+        }
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn delete_user(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    if id != user_id {
+        return Json(forbidden_user(id));
+    }
+
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let count = users::delete_user(&mut client, id).unwrap();
+
+        json!({ "success": count > 0 })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct NewApiToken {
+    name: String,
+    scope: String,
+}
+
+async fn create_api_token(AuthUser(user_id, _): AuthUser, Json(payload): Json<NewApiToken>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        if Scope::parse(&payload.scope).is_none() {
+            return json!({
+                "success": false,
+                "message": format!("Invalid scope: {}", payload.scope)
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let token = auth::generate_token();
+        let id = api_tokens::insert_api_token(&mut client, user_id, &payload.name, &auth::hash_token(&token), &payload.scope).unwrap();
+
+        json!({
+            "success": true,
+            "id": id,
+            "name": payload.name,
+            "scope": payload.scope,
+            "token": token
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn list_api_tokens(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let tokens = api_tokens::get_api_tokens_for_user(&mut client, user_id).unwrap();
+
+        json!({ "tokens": tokens })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn revoke_api_token(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let count = api_tokens::delete_api_token(&mut client, user_id, id).unwrap();
+
+        if count == 0 {
+            return json!({
+                "success": false,
+                "message": format!("API token {} not found", id)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+fn word_update_error(err: UpdateError, message: String) -> serde_json::Value {
+    match err {
+        UpdateError::NotFound => json!({
+            "success": false,
+            "message": "Word not found"
+        }),
+        UpdateError::Conflict => json!({
+            "success": false,
+            "message": message
+        }),
+        UpdateError::Db(err) => panic!("{}", err),
+    }
+}
+
+#[derive(Deserialize)]
+struct MergeWords {
+    from_id: i32,
+    into_id: i32,
+}
+
+async fn merge_words(AuthUser(user_id, _): AuthUser, Json(payload): Json<MergeWords>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        match words::merge_words(&mut client, payload.from_id, payload.into_id, Some(user_id)) {
+            Ok(word) => json!({
+                "success": true,
+                "id": word.id,
+                "text": word.text,
+                "language": word.language
+            }),
+            Err(err) if payload.from_id == payload.into_id => word_update_error(err, "Cannot merge a word into itself".to_string()),
+            Err(err) => word_update_error(err, "Cannot merge words from different languages".to_string()),
+        }
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct SplitWord {
+    lemma_id: i32,
+    form: String,
+}
+
+async fn split_word(AuthUser(user_id, _): AuthUser, Json(payload): Json<SplitWord>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let Some(form) = normalize_word(&payload.form) else {
+            return json!({
+                "success": false,
+                "message": format!("Invalid word: '{}'", payload.form)
+            });
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        match words::split_word(&mut client, payload.lemma_id, &form, Some(user_id)) {
+            Ok(word) => json!({
+                "success": true,
+                "id": word.id,
+                "text": word.text,
+                "language": word.language
+            }),
+            Err(err) => word_update_error(err, format!("Word '{}' already exists", form)),
+        }
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn word_audit() -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let records = word_audit::get_word_audit(&mut client, 100).unwrap();
+
+        json!({ "records": records })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct ExportQuery {
+    format: Option<String>,
+    language: Option<String>,
+    status: Option<String>,
+    list: Option<i32>,
+}
+
+async fn export_vocabulary(AuthUser(user_id, _): AuthUser, Query(query): Query<ExportQuery>) -> Response {
+    let format_name = query.format.unwrap_or_else(|| "csv".to_string());
+    let Some(format) = ExportFormat::parse(&format_name) else {
+        return Json(json!({
+            "success": false,
+            "message": format!("Invalid format: {}", format_name)
+        })).into_response();
+    };
+
+    if let Some(status) = query.status.as_deref().filter(|status| *status != "known" && *status != "unknown") {
+        return Json(json!({
+            "success": false,
+            "message": format!("Invalid status: {}", status)
+        })).into_response();
+    }
+
+    let body = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = query.language.as_deref().and_then(|language| unknown_language(&mut client, language)) {
+            return Err(error);
+        }
+
+        if let Some(error) = query.list.and_then(|list_id| missing_list(&mut client, user_id, list_id)) {
+            return Err(error);
+        }
+
+        let entries = export::collect_entries(&mut client, user_id, query.language.as_deref(), query.status.as_deref(), query.list).unwrap();
+        Ok(export::render(&entries, format))
+    }).await.unwrap();
+
+    let body = match body {
+        Ok(body) => body,
+        Err(error) => return Json(error).into_response(),
+    };
+
+    let disposition = format!("attachment; filename=\"vocabulary.{}\"", format.extension());
+
+    (
+        [(CONTENT_TYPE, format.content_type().to_string()), (CONTENT_DISPOSITION, disposition)],
+        body,
+    ).into_response()
+}
+
+#[derive(Deserialize)]
+struct ImportPayload {
+    language: String,
+    format: String,
+    content: String,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+async fn import_vocabulary(AuthUser(user_id, _): AuthUser, Json(payload): Json<ImportPayload>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let Some(format) = ImportFormat::parse(&payload.format) else {
+            return json!({
+                "success": false,
+                "message": format!("Invalid format: {}", payload.format)
+            });
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = unknown_language(&mut client, &payload.language) {
+            return error;
+        }
+
+        let entries = import::parse_entries(&payload.content, format);
+        let report = import::import_words(&mut client, user_id, &payload.language, &entries, payload.dry_run).unwrap();
+
+        json!({
+            "success": true,
+            "dry_run": payload.dry_run,
+            "new": report.new,
+            "existing": report.existing,
+            "rejected": report.rejected
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn backup_account(AuthUser(user_id, _): AuthUser) -> Response {
+    let backup = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        backup::create_backup(&mut client, user_id).unwrap()
+    }).await.unwrap();
+
+    (
+        [(CONTENT_DISPOSITION, "attachment; filename=\"vocab_analyser_backup.json\"")],
+        Json(backup),
+    ).into_response()
+}
+
+async fn restore_account(AuthUser(user_id, _): AuthUser, Json(backup): Json<Backup>) -> Json<serde_json::Value> {
+    if backup.version > BACKUP_VERSION {
+        return Json(json!({
+            "success": false,
+            "message": format!("Unsupported backup version: {}", backup.version)
+        }));
+    }
+
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let report = backup::restore_backup(&mut client, user_id, &backup).unwrap();
+
+        json!({
+            "success": true,
+            "words": report.words,
+            "texts": report.texts,
+            "profiles": report.profiles,
+            "annotations": report.annotations,
+            "lists": report.lists,
+            "subscriptions": report.subscriptions,
+            "skipped": report.skipped,
+            "skipped_entries": report.skipped_entries
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+struct UploadedFile {
+    name: Option<String>,
+    bytes: Vec<u8>,
+}
+
+async fn read_upload(mut multipart: Multipart, max_file_size: usize) -> Result<(Option<String>, Vec<UploadedFile>), String> {
+    let mut language = None;
+    let mut files = Vec::new();
+
+    // A truncated body or one over the route's limit surfaces as an error here
+    // and must not be taken for the end of the upload.
+    let invalid = |err: axum::extract::multipart::MultipartError| format!("Invalid upload: {}", err.body_text());
+
+    while let Some(mut field) = multipart.next_field().await.map_err(invalid)? {
+        match field.name() {
+            Some("language") => language = Some(field.text().await.map_err(invalid)?),
+            Some("file") => {
+                let name = field.file_name().map(str::to_string);
+                let mut bytes = Vec::new();
+                while let Some(chunk) = field.chunk().await.map_err(invalid)? {
+                    if bytes.len() + chunk.len() > max_file_size {
+                        return Err(format!("File exceeds the {} MB limit", max_file_size / 1024 / 1024));
+                    }
+                    bytes.extend_from_slice(&chunk);
+                }
+                files.push(UploadedFile { name, bytes });
+            }
+            _ => {}
+        }
+    }
+
+    Ok((language, files))
+}
+
+async fn upload_epub(AuthUser(user_id, _): AuthUser, multipart: Multipart) -> Json<serde_json::Value> {
+    let (language, files) = match read_upload(multipart, MAX_ARCHIVE_SIZE).await {
+        Ok(upload) => upload,
+        Err(message) => return Json(json!({"success": false, "message": message})),
+    };
+    let Some(file) = files.into_iter().next() else {
+        return Json(json!({"success": false, "message": "A file field is required"}));
+    };
+
+    let response_json = task::spawn_blocking(move || {
+        let book = match parse_epub(&file.bytes) {
+            Ok(book) => book,
+            Err(err) => return json!({"success": false, "message": err.to_string()}),
+        };
+
+        let sample: String = book.chapters.iter().map(|chapter| chapter.text.as_str()).collect::<Vec<_>>().join("\n");
+        let (language, warning) = match langdetect::resolve_language(language.as_deref(), &sample) {
+            Ok(resolved) => resolved,
+            Err(message) => return json!({"success": false, "message": message}),
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        let mut chapters = Vec::new();
+        for (index, chapter) in book.chapters.iter().enumerate() {
+            let chapter_title = chapter.title.clone().unwrap_or_else(|| format!("Chapter {}", index + 1));
+            let title = match &book.title {
+                Some(book_title) => format!("{}: {}", book_title, chapter_title),
+                None => chapter_title,
+            };
+
+            let text_id = texts::insert_text(&mut client, user_id, &language, Some(&title), &chapter.text).unwrap();
+            let coverage = compute_coverage(&mut client, user_id, &language, &chapter.text).unwrap();
+            words::insert_multiple_words(&mut client, &coverage.unknown_words, &language).unwrap();
+
+            chapters.push(json!({
+                "text_id": text_id,
+                "title": title,
+                "coverage": coverage
+            }));
+        }
+
+        json!({
+            "success": true,
+            "title": book.title,
+            "language": language,
+            "warning": warning,
+            "chapters": chapters
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn upload_subtitles(AuthUser(user_id, _): AuthUser, multipart: Multipart) -> Json<serde_json::Value> {
+    let (language, files) = match read_upload(multipart, MAX_TEXT_SIZE).await {
+        Ok(upload) => upload,
+        Err(message) => return Json(json!({"success": false, "message": message})),
+    };
+    if files.is_empty() {
+        return Json(json!({"success": false, "message": "A file field is required"}));
+    }
+
+    let response_json = task::spawn_blocking(move || {
+        let parsed: Vec<(String, Vec<subtitles::Cue>)> = files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| {
+                let title = file.name.clone().unwrap_or_else(|| format!("Episode {}", index + 1));
+                let (content, _) = encoding::decode(&file.bytes);
+                (title, subtitles::parse_subtitles(&content))
+            })
+            .collect();
+
+        let sample: String = parsed.iter().map(|(_, cues)| subtitles::cues_text(cues)).collect::<Vec<_>>().join("\n");
+        let (language, warning) = match langdetect::resolve_language(language.as_deref(), &sample) {
+            Ok(resolved) => resolved,
+            Err(message) => return json!({"success": false, "message": message}),
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        let mut episodes = Vec::new();
+        for (title, cues) in parsed {
+            if cues.is_empty() {
+                episodes.push(json!({"title": title, "success": false, "message": "No subtitle cues found"}));
+                continue;
+            }
+
+            let text = subtitles::cues_text(&cues);
+            let text_id = texts::insert_text(&mut client, user_id, &language, Some(&title), &text).unwrap();
+            let coverage = compute_coverage(&mut client, user_id, &language, &text).unwrap();
+            words::insert_multiple_words(&mut client, &coverage.unknown_words, &language).unwrap();
+
+            let mut timestamps = subtitles::timestamps_by_word(&cues);
+            let unknown_words: Vec<serde_json::Value> = coverage
+                .unknown_words
+                .iter()
+                .map(|word| json!({"word": word, "timestamps": timestamps.remove(word).unwrap_or_default()}))
+                .collect();
+
+            episodes.push(json!({
+                "title": title,
+                "success": true,
+                "text_id": text_id,
+                "cues": cues.len(),
+                "coverage": coverage,
+                "unknown_words": unknown_words
+            }));
+        }
+
+        json!({
+            "success": true,
+            "language": language,
+            "warning": warning,
+            "episodes": episodes
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn upload_article(AuthUser(user_id, _): AuthUser, multipart: Multipart) -> Json<serde_json::Value> {
+    let (language, files) = match read_upload(multipart, MAX_TEXT_SIZE).await {
+        Ok(upload) => upload,
+        Err(message) => return Json(json!({"success": false, "message": message})),
+    };
+    let Some(file) = files.into_iter().next() else {
+        return Json(json!({"success": false, "message": "A file field is required"}));
+    };
+
+    let response_json = task::spawn_blocking(move || {
+        let (content, _) = encoding::decode(&file.bytes);
+        let is_markdown = match file.name.as_deref().and_then(|name| name.rsplit_once('.')) {
+            Some((_, extension)) => matches!(extension.to_lowercase().as_str(), "md" | "markdown"),
+            None => !content.trim_start().starts_with('<'),
+        };
+        let document = if is_markdown {
+            html::markdown_to_text(&content)
+        } else {
+            html::extract_article(&content)
+        };
+
+        if document.text.is_empty() {
+            return json!({"success": false, "message": "No readable content found"});
+        }
+
+        let (language, warning) = match langdetect::resolve_language(language.as_deref(), &document.text) {
+            Ok(resolved) => resolved,
+            Err(message) => return json!({"success": false, "message": message}),
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        let text_id = texts::insert_text(&mut client, user_id, &language, document.title.as_deref(), &document.text).unwrap();
+        let coverage = compute_coverage(&mut client, user_id, &language, &document.text).unwrap();
+        words::insert_multiple_words(&mut client, &coverage.unknown_words, &language).unwrap();
+
+        json!({
+            "success": true,
+            "text_id": text_id,
+            "title": document.title,
+            "language": language,
+            "warning": warning,
+            "text": document.text,
+            "coverage": coverage
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn upload_text(AuthUser(user_id, _): AuthUser, multipart: Multipart) -> Json<serde_json::Value> {
+    let (language, files) = match read_upload(multipart, MAX_TEXT_SIZE).await {
+        Ok(upload) => upload,
+        Err(message) => return Json(json!({"success": false, "message": message})),
+    };
+    let Some(file) = files.into_iter().next() else {
+        return Json(json!({"success": false, "message": "A file field is required"}));
+    };
+
+    let response_json = task::spawn_blocking(move || {
+        let (content, encoding) = encoding::decode(&file.bytes);
+        let (language, warning) = match langdetect::resolve_language(language.as_deref(), &content) {
+            Ok(resolved) => resolved,
+            Err(message) => return json!({"success": false, "message": message}),
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        let text_id = texts::insert_text(&mut client, user_id, &language, file.name.as_deref(), &content).unwrap();
+        let coverage = compute_coverage(&mut client, user_id, &language, &content).unwrap();
+        words::insert_multiple_words(&mut client, &coverage.unknown_words, &language).unwrap();
+
+        json!({
+            "success": true,
+            "text_id": text_id,
+            "title": file.name,
+            "language": language,
+            "warning": warning,
+            "encoding": encoding,
+            "bytes": file.bytes.len(),
+            "coverage": coverage
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+fn unknown_language(client: &mut Client, code: &str) -> Option<serde_json::Value> {
+    if languages::get_language(client, code).unwrap().is_some() {
+        return None;
+    }
+
+    Some(json!({
+        "success": false,
+        "message": format!("Unknown language code: {}", code)
+    }))
+}
+
+async fn list_languages() -> Json<serde_json::Value> {
+    let languages = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        languages::get_languages(&mut client).unwrap()
+    }).await.unwrap();
+
+    Json(json!({"languages": languages}))
+}
+
+async fn list_profiles(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let profiles = profiles::get_profiles_for_user(&mut client, user_id).unwrap();
+
+        json!({ "profiles": profiles })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct Profile {
+    native_language: Option<String>,
+    daily_goal: Option<i32>,
+}
+
+async fn save_profile(AuthUser(user_id, _): AuthUser, Path(language): Path<String>, Json(payload): Json<Profile>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let daily_goal = payload.daily_goal.unwrap_or(0);
+        if daily_goal < 0 {
+            return json!({
+                "success": false,
+                "message": format!("Invalid daily goal: {}", daily_goal)
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        for code in [Some(&language), payload.native_language.as_ref()].into_iter().flatten() {
+            if let Some(error) = unknown_language(&mut client, code) {
+                return error;
+            }
+        }
+
+        let profile = profiles::upsert_profile(&mut client, user_id, &language, payload.native_language.as_deref(), daily_goal).unwrap();
+
+        json!({ "success": true, "profile": profile })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn delete_profile(AuthUser(user_id, _): AuthUser, Path(language): Path<String>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let count = profiles::delete_profile(&mut client, user_id, &language).unwrap();
+
+        if count == 0 {
+            return json!({
+                "success": false,
+                "message": format!("No profile for language {}", language)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct StatsQuery {
+    language: Option<String>,
+    days: Option<u32>,
+}
+
+async fn learning_stats(AuthUser(user_id, _): AuthUser, Query(query): Query<StatsQuery>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let days = query.days.unwrap_or(30);
+        if !(1..=366).contains(&days) {
+            return json!({
+                "success": false,
+                "message": format!("Invalid number of days: {}", days)
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = query.language.as_deref().and_then(|language| unknown_language(&mut client, language)) {
+            return error;
+        }
+
+        let today = chrono::Utc::now().date_naive();
+        let stats = stats::compute_stats(&mut client, user_id, query.language.as_deref(), today, days).unwrap();
+
+        json!({ "success": true, "stats": stats })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct RankingQuery {
+    language: Option<String>,
+    ids: Option<String>,
+}
+
+async fn text_ranking(AuthUser(user_id, _): AuthUser, Query(query): Query<RankingQuery>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let ids: Option<Vec<i32>> = match query.ids.as_deref().map(|ids| ids.split(',').map(|id| id.trim().parse::<i32>()).collect()) {
+            Some(Ok(ids)) => Some(ids),
+            Some(Err(_)) => {
+                return json!({
+                    "success": false,
+                    "message": format!("Invalid text ids: '{}'", query.ids.unwrap_or_default())
+                });
+            }
+            None => None,
+        };
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = query.language.as_deref().and_then(|language| unknown_language(&mut client, language)) {
+            return error;
+        }
+
+        let mut user_texts = texts::get_texts_for_user(&mut client, user_id, query.language.as_deref()).unwrap();
+        if let Some(ids) = ids {
+            if let Some(missing) = ids.iter().find(|id| !user_texts.iter().any(|text| text.id == **id)) {
+                return json!({
+                    "success": false,
+                    "message": format!("Text {} not found", missing)
+                });
+            }
+            user_texts.retain(|text| ids.contains(&text.id));
+        }
+
+        let ranking = rank_texts(&mut client, user_id, &user_texts).unwrap();
+
+        json!({ "success": true, "texts": ranking })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    word: Option<String>,
+    language: Option<String>,
+    limit: Option<i64>,
+}
+
+async fn vocabulary_history(AuthUser(user_id, _): AuthUser, Query(query): Query<HistoryQuery>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let limit = query.limit.unwrap_or(50);
+        if limit < 1 {
+            return json!({
+                "success": false,
+                "message": format!("Invalid limit: {}", limit)
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let word_id = match (query.word, query.language) {
+            (None, _) => None,
+            (Some(_), None) => {
+                return json!({
+                    "success": false,
+                    "message": "A language is required to look up a word"
+                });
+            }
+            (Some(text), Some(language)) => {
+                if let Some(error) = unknown_language(&mut client, &language) {
+                    return error;
+                }
+
+                let word = normalize_word(&text).unwrap_or(text);
+                let Some(word_id) = words::get_id_word(&mut client, &word, &language).unwrap() else {
+                    return json!({ "success": true, "events": [] });
+                };
+                Some(word_id)
+            }
+        };
+
+        let events = history::get_history(&mut client, user_id, word_id, limit).unwrap();
+
+        json!({ "success": true, "events": events })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct Undo {
+    count: Option<i64>,
+}
+
+async fn undo_changes(AuthUser(user_id, _): AuthUser, Json(payload): Json<Undo>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let count = payload.count.unwrap_or(1);
+        if count < 1 {
+            return json!({
+                "success": false,
+                "message": format!("Invalid count: {}", count)
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let events = history::undo_last(&mut client, user_id, count).unwrap();
+
+        json!({
+            "success": true,
+            "undone": events.len(),
+            "events": events
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn replay_history(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let report = history::replay(&mut client, user_id).unwrap();
+
+        json!({ "success": true, "report": report })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct DictionaryImport {
+    name: String,
+    format: String,
+    source_language: String,
+    target_language: String,
+}
+
+async fn import_dictionary(Query(query): Query<DictionaryImport>, multipart: Multipart) -> Json<serde_json::Value> {
+    let Some(format) = dictionary::DictionaryFormat::parse(&query.format) else {
+        return Json(json!({"success": false, "message": format!("Invalid format: {}", query.format)}));
+    };
+
+    let (_, files) = match read_upload(multipart, MAX_ARCHIVE_SIZE).await {
+        Ok(upload) => upload,
+        Err(message) => return Json(json!({"success": false, "message": message})),
+    };
+    if files.is_empty() {
+        return Json(json!({"success": false, "message": "A file field is required"}));
+    }
+
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        for code in [&query.source_language, &query.target_language] {
+            if let Some(error) = unknown_language(&mut client, code) {
+                return error;
+            }
+        }
+
+        let files: Vec<(String, Vec<u8>)> = files
+            .into_iter()
+            .map(|file| (file.name.unwrap_or_default(), file.bytes))
+            .collect();
+        let entries = match dictionary::parse_files(format, &files, &query.source_language) {
+            Ok(entries) => entries,
+            Err(err) => return json!({"success": false, "message": err.to_string()}),
+        };
+
+        let imported = dictionaries::import_dictionary(
+            &mut client, &query.name, &query.source_language, &query.target_language, format.name(), &entries
+        ).unwrap();
+
+        json!({ "success": true, "dictionary": imported })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn delete_dictionary(Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if dictionaries::delete_dictionary(&mut client, id).unwrap() == 0 {
+            return json!({
+                "success": false,
+                "message": format!("Dictionary {} not found", id)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn list_dictionaries() -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let dictionaries = dictionaries::get_dictionaries(&mut client).unwrap();
+
+        json!({ "dictionaries": dictionaries })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct LookupQuery {
+    word: String,
+    language: String,
+    target: Option<String>,
+}
+
+async fn lookup_word(Query(query): Query<LookupQuery>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        for code in [Some(&query.language), query.target.as_ref()].into_iter().flatten() {
+            if let Some(error) = unknown_language(&mut client, code) {
+                return error;
+            }
+        }
+
+        let word = dictionary::normalize_headword(&query.word);
+        let definitions = dictionaries::lookup(&mut client, &query.language, query.target.as_deref(), &word).unwrap();
+
+        json!({
+            "success": true,
+            "word": word,
+            "definitions": definitions
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct AnnotationQuery {
+    q: Option<String>,
+    tag: Option<String>,
+    language: Option<String>,
+}
+
+async fn search_annotations(AuthUser(user_id, _): AuthUser, Query(query): Query<AnnotationQuery>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let search = query.q.as_deref().map(str::trim).filter(|search| !search.is_empty());
+        let annotations = annotations::search_annotations(&mut client, user_id, search, query.tag.as_deref(), query.language.as_deref()).unwrap();
+
+        json!({ "success": true, "annotations": annotations })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+fn annotated_word(client: &mut Client, language: &str, word: &str) -> Result<String, serde_json::Value> {
+    if let Some(error) = unknown_language(client, language) {
+        return Err(error);
+    }
+
+    normalize_word(word).ok_or_else(|| json!({
+        "success": false,
+        "message": format!("Invalid word: '{}'", word)
+    }))
+}
+
+async fn get_annotation(AuthUser(user_id, _): AuthUser, Path((language, word)): Path<(String, String)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let word = match annotated_word(&mut client, &language, &word) {
+            Ok(word) => word,
+            Err(error) => return error,
+        };
+
+        let annotation = match words::get_id_word(&mut client, &word, &language).unwrap() {
+            Some(word_id) => annotations::get_annotation(&mut client, user_id, word_id).unwrap(),
+            None => None,
+        };
+
+        match annotation {
+            Some(annotation) => json!({ "success": true, "annotation": annotation }),
+            None => json!({
+                "success": false,
+                "message": format!("No annotation for '{}'", word)
+            }),
+        }
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct AnnotationPayload {
+    translation: Option<String>,
+    note: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+async fn save_annotation(AuthUser(user_id, _): AuthUser, Path((language, word)): Path<(String, String)>, Json(payload): Json<AnnotationPayload>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let word = match annotated_word(&mut client, &language, &word) {
+            Ok(word) => word,
+            Err(error) => return error,
+        };
+
+        let translation = payload.translation.as_deref().map(str::trim).filter(|translation| !translation.is_empty());
+        let note = payload.note.as_deref().map(str::trim).filter(|note| !note.is_empty());
+
+        let word_id = words::insert_word(&mut client, &word, &language).unwrap();
+        let annotation = annotations::upsert_annotation(&mut client, user_id, word_id, translation, note, &payload.tags).unwrap();
+
+        json!({ "success": true, "annotation": annotation })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn delete_annotation(AuthUser(user_id, _): AuthUser, Path((language, word)): Path<(String, String)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let word = match annotated_word(&mut client, &language, &word) {
+            Ok(word) => word,
+            Err(error) => return error,
+        };
+
+        let count = match words::get_id_word(&mut client, &word, &language).unwrap() {
+            Some(word_id) => annotations::delete_annotation(&mut client, user_id, word_id).unwrap(),
+            None => 0,
+        };
+
+        if count == 0 {
+            return json!({
+                "success": false,
+                "message": format!("No annotation for '{}'", word)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+// Reading and filtering by a list works on public lists too; changing one is
+// left to its owner.
+fn missing_list(client: &mut Client, user_id: i32, list_id: i32) -> Option<serde_json::Value> {
+    if word_lists::get_visible_list(client, user_id, list_id).unwrap().is_some() {
+        return None;
+    }
+
+    Some(json!({
+        "success": false,
+        "message": format!("Word list {} not found", list_id)
+    }))
+}
+
+fn missing_own_list(client: &mut Client, user_id: i32, list_id: i32) -> Option<serde_json::Value> {
+    if word_lists::get_list(client, user_id, list_id).unwrap().is_some() {
+        return None;
+    }
+
+    Some(json!({
+        "success": false,
+        "message": format!("Word list {} not found", list_id)
+    }))
+}
+
+async fn list_word_lists(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let lists = word_lists::get_lists_for_user(&mut client, user_id).unwrap();
+
+        json!({ "lists": lists })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn list_public_word_lists(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let lists = word_lists::get_public_lists(&mut client, user_id).unwrap();
+
+        json!({ "lists": lists })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn list_subscribed_word_lists(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let lists = word_lists::get_subscribed_lists(&mut client, user_id).unwrap();
+
+        json!({ "lists": lists })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn get_word_list(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let Some(list) = word_lists::get_visible_list(&mut client, user_id, id).unwrap() else {
+            return json!({
+                "success": false,
+                "message": format!("Word list {} not found", id)
+            });
+        };
+        let words = word_lists::get_list_words(&mut client, user_id, id).unwrap();
+
+        json!({
+            "success": true,
+            "list": list,
+            "words": words
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct WordListPayload {
+    name: String,
+    description: Option<String>,
+    is_public: Option<bool>,
+}
+
+// Only the fields present in the body are changed; an explicit null clears
+// the description.
+#[derive(Deserialize)]
+struct WordListChanges {
+    name: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    description: Option<Option<String>>,
+    is_public: Option<bool>,
+}
+
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Option<String>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+fn word_list_response(result: Result<word_lists::WordList, UpdateError>, name: &str, id: Option<i32>) -> serde_json::Value {
+    match result {
+        Ok(list) => json!({ "success": true, "list": list }),
+        Err(UpdateError::Conflict) => json!({
+            "success": false,
+            "message": format!("A word list named '{}' already exists", name)
+        }),
+        Err(UpdateError::NotFound) => json!({
+            "success": false,
+            "message": format!("Word list {} not found", id.unwrap_or_default())
+        }),
+        Err(UpdateError::Db(err)) => panic!("{}", err),
+    }
+}
+
+async fn create_word_list(AuthUser(user_id, _): AuthUser, Json(payload): Json<WordListPayload>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let name = payload.name.trim();
+        if name.is_empty() {
+            return json!({
+                "success": false,
+                "message": "Word list name must not be empty"
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let result = word_lists::create_list(&mut client, user_id, name, payload.description.as_deref(), payload.is_public.unwrap_or(false));
+        word_list_response(result, name, None)
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn update_word_list(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Json(payload): Json<WordListChanges>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let name = payload.name.as_deref().map(str::trim);
+        if name == Some("") {
+            return json!({
+                "success": false,
+                "message": "Word list name must not be empty"
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let description = payload.description.as_ref().map(Option::as_deref);
+        let result = word_lists::update_list(&mut client, user_id, id, name, description, payload.is_public);
+        word_list_response(result, name.unwrap_or_default(), Some(id))
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn delete_word_list(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if word_lists::delete_list(&mut client, user_id, id).unwrap() == 0 {
+            return json!({
+                "success": false,
+                "message": format!("Word list {} not found", id)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct ListWords {
+    language: String,
+    words: Vec<String>,
+}
+
+// Normalizes the requested words, returning the valid ones and the rejected
+// entries as typed.
+fn split_words(requested: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut valid = Vec::new();
+    let mut rejected = Vec::new();
+    for entry in requested {
+        match normalize_word(&entry) {
+            Some(word) if !valid.contains(&word) => valid.push(word),
+            Some(_) => {}
+            None => rejected.push(entry),
+        }
+    }
+    (valid, rejected)
+}
+
+async fn add_list_words(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Json(payload): Json<ListWords>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = missing_own_list(&mut client, user_id, id).or_else(|| unknown_language(&mut client, &payload.language)) {
+            return error;
+        }
+
+        let (valid, rejected) = split_words(payload.words);
+        words::insert_multiple_words(&mut client, &valid, &payload.language).unwrap();
+        let word_ids: Vec<i32> = words::get_id_words(&mut client, &valid, &payload.language).unwrap().into_iter().flatten().collect();
+        let added = word_lists::add_words(&mut client, id, &word_ids).unwrap();
+
+        json!({
+            "success": true,
+            "added": added,
+            "rejected": rejected
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn remove_list_words(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Json(payload): Json<ListWords>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = missing_own_list(&mut client, user_id, id).or_else(|| unknown_language(&mut client, &payload.language)) {
+            return error;
+        }
+
+        let (valid, rejected) = split_words(payload.words);
+        let word_ids: Vec<i32> = words::get_id_words(&mut client, &valid, &payload.language).unwrap().into_iter().flatten().collect();
+        let removed = word_lists::remove_words(&mut client, id, &word_ids).unwrap();
+
+        json!({
+            "success": true,
+            "removed": removed,
+            "rejected": rejected
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn subscribe_word_list(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = missing_list(&mut client, user_id, id) {
+            return error;
+        }
+
+        let subscribed = word_lists::subscribe(&mut client, user_id, id).unwrap();
+
+        json!({
+            "success": true,
+            "subscribed": subscribed == 1
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn unsubscribe_word_list(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if word_lists::unsubscribe(&mut client, user_id, id).unwrap() == 0 {
+            return json!({
+                "success": false,
+                "message": format!("Not subscribed to word list {}", id)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn word_list_progress(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = missing_list(&mut client, user_id, id) {
+            return error;
+        }
+
+        let progress = word_lists::get_list_progress(&mut client, user_id, id).unwrap();
+        let unknown: Vec<word_lists::ListWord> = word_lists::get_list_words(&mut client, user_id, id)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| !entry.known)
+            .collect();
+
+        json!({
+            "success": true,
+            "progress": progress,
+            "unknown": unknown
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+// Members can see a group and its texts; managing it and looking at the
+// students' vocabulary is left to its teachers.
+fn missing_group(client: &mut Client, user_id: i32, group_id: i32) -> Option<serde_json::Value> {
+    if groups::get_group(client, user_id, group_id).unwrap().is_some() {
+        return None;
+    }
+
+    Some(json!({
+        "success": false,
+        "message": format!("Group {} not found", group_id)
+    }))
+}
+
+fn not_teacher(client: &mut Client, user_id: i32, group_id: i32) -> Option<serde_json::Value> {
+    match groups::get_group(client, user_id, group_id).unwrap() {
+        Some(group) if group.role == "teacher" => None,
+        Some(_) => Some(json!({
+            "success": false,
+            "message": format!("Only teachers can do this in group {}", group_id)
+        })),
+        None => missing_group(client, user_id, group_id),
+    }
+}
+
+async fn list_groups(AuthUser(user_id, _): AuthUser) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let groups = groups::get_groups_for_user(&mut client, user_id).unwrap();
+
+        json!({ "groups": groups })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn get_group(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let Some(group) = groups::get_group(&mut client, user_id, id).unwrap() else {
+            return json!({
+                "success": false,
+                "message": format!("Group {} not found", id)
+            });
+        };
+        let members = groups::get_members(&mut client, id).unwrap();
+
+        json!({
+            "success": true,
+            "group": group,
+            "members": members
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct NewGroup {
+    name: String,
+}
+
+async fn create_group(AuthUser(user_id, _): AuthUser, Json(payload): Json<NewGroup>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let name = payload.name.trim();
+        if name.is_empty() {
+            return json!({
+                "success": false,
+                "message": "Group name must not be empty"
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let group = groups::create_group(&mut client, user_id, name).unwrap();
+
+        json!({ "success": true, "group": group })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn delete_group(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id) {
+            return error;
+        }
+
+        groups::delete_group(&mut client, id).unwrap();
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct NewMember {
+    user: String,
+    role: Option<String>,
+}
+
+async fn add_group_member(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Json(payload): Json<NewMember>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let role = payload.role.as_deref().unwrap_or("student");
+        if !groups::ROLES.contains(&role) {
+            return json!({
+                "success": false,
+                "message": format!("Invalid role '{}'. Use one of: {}", role, groups::ROLES.join(", "))
+            });
+        }
+
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id) {
+            return error;
+        }
+
+        let Some(member_id) = users::get_user_by_name(&mut client, &payload.user).unwrap() else {
+            return json!({
+                "success": false,
+                "message": format!("User '{}' not found", payload.user)
+            });
+        };
+        if member_id == user_id && role != "teacher" {
+            return json!({
+                "success": false,
+                "message": "Teachers cannot demote themselves"
+            });
+        }
+
+        let changed = groups::add_member(&mut client, id, member_id, role).unwrap();
+        let pending = groups::get_members(&mut client, id)
+            .unwrap()
+            .into_iter()
+            .any(|member| member.id == member_id && member.pending);
 
-    let mut client = tokio::task::spawn_blocking(move || {
+        json!({
+            "success": true,
+            "changed": changed == 1,
+            "pending": pending
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+// Teachers remove anyone but the group's last teacher; students can only
+// leave.
+async fn remove_group_member(AuthUser(user_id, _): AuthUser, Path((id, member_id)): Path<(i32, i32)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
         let mut client = Client::connect(&db_url, NoTls).unwrap();
 
-        db::init_tables(&mut client).expect("Falha ao criar tabelas");
+        let check = if member_id == user_id {
+            missing_group(&mut client, user_id, id)
+        } else {
+            not_teacher(&mut client, user_id, id)
+        };
+        if let Some(error) = check {
+            return error;
+        }
+
+        let members = groups::get_members(&mut client, id).unwrap();
+        let Some(member) = members.iter().find(|member| member.id == member_id) else {
+            return json!({
+                "success": false,
+                "message": format!("User {} is not a member of group {}", member_id, id)
+            });
+        };
+        let teachers = members.iter().filter(|member| member.role == "teacher" && !member.pending).count();
+        if member.role == "teacher" && !member.pending && teachers == 1 {
+            return json!({
+                "success": false,
+                "message": "A group needs at least one teacher; delete the group instead"
+            });
+        }
 
-        client
+        groups::remove_member(&mut client, id, member_id).unwrap();
+
+        json!({ "success": true })
     }).await.unwrap();
 
-    let app = Router::new()
-        .route("/", get(|| async {"Hello, World!"}))
-        .route("/analyze-text", post(analyse_text))
-        .route("/toggle-word", post(toggle_word_knowledge))
-        .layer(cors);
+    Json(response_json)
+}
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+async fn accept_group_invitation(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if groups::accept_invitation(&mut client, id, user_id).unwrap() == 0 {
+            return json!({
+                "success": false,
+                "message": format!("No pending invitation to group {}", id)
+            });
+        }
+
+        let group = groups::get_group(&mut client, user_id, id).unwrap();
+
+        json!({ "success": true, "group": group })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn decline_group_invitation(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if groups::decline_invitation(&mut client, id, user_id).unwrap() == 0 {
+            return json!({
+                "success": false,
+                "message": format!("No pending invitation to group {}", id)
+            });
+        }
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct ProgressQuery {
+    language: Option<String>,
+}
+
+async fn group_progress(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Query(query): Query<ProgressQuery>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id)
+            .or_else(|| query.language.as_deref().and_then(|language| unknown_language(&mut client, language)))
+        {
+            return error;
+        }
+
+        let students = groups::get_student_progress(&mut client, id, query.language.as_deref()).unwrap();
+
+        json!({
+            "success": true,
+            "language": query.language,
+            "students": students
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn group_word_knowledge(AuthUser(user_id, _): AuthUser, Path((id, language, word)): Path<(i32, String, String)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id) {
+            return error;
+        }
+
+        let word = match annotated_word(&mut client, &language, &word) {
+            Ok(word) => word,
+            Err(error) => return error,
+        };
+
+        let word_id = words::get_id_word(&mut client, &word, &language).unwrap();
+        let knowledge = groups::get_word_knowledge(&mut client, id, word_id).unwrap();
+
+        json!({
+            "success": true,
+            "word": word,
+            "language": language,
+            "known_by": knowledge.known_by,
+            "unknown_to": knowledge.unknown_to
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn list_group_texts(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = missing_group(&mut client, user_id, id) {
+            return error;
+        }
+
+        let texts = groups::get_assigned_texts(&mut client, id).unwrap();
+
+        json!({ "success": true, "texts": texts })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+fn missing_assignment(client: &mut Client, group_id: i32, text_id: i32) -> Option<serde_json::Value> {
+    if groups::is_assigned(client, group_id, text_id).unwrap() {
+        return None;
+    }
+
+    Some(json!({
+        "success": false,
+        "message": format!("Text {} is not assigned to group {}", text_id, group_id)
+    }))
+}
+
+// Students read assigned texts through the group, with the coverage of their
+// own vocabulary.
+async fn get_group_text(AuthUser(user_id, _): AuthUser, Path((id, text_id)): Path<(i32, i32)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = missing_group(&mut client, user_id, id).or_else(|| missing_assignment(&mut client, id, text_id)) {
+            return error;
+        }
+
+        let text = texts::get_text(&mut client, text_id).unwrap().unwrap();
+        let coverage = compute_coverage(&mut client, user_id, &text.language, &text.content).unwrap();
+
+        json!({
+            "success": true,
+            "id": text.id,
+            "title": text.title,
+            "language": text.language,
+            "content": text.content,
+            "coverage": coverage
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct Assignment {
+    text_id: i32,
+}
+
+async fn assign_group_text(AuthUser(user_id, _): AuthUser, Path(id): Path<i32>, Json(payload): Json<Assignment>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id) {
+            return error;
+        }
+
+        match texts::get_text(&mut client, payload.text_id).unwrap() {
+            Some(text) if text.id_user == user_id => {}
+            _ => {
+                return json!({
+                    "success": false,
+                    "message": format!("Text {} not found", payload.text_id)
+                });
+            }
+        }
+
+        let assigned = groups::assign_text(&mut client, id, payload.text_id).unwrap();
+
+        json!({
+            "success": true,
+            "assigned": assigned == 1
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn unassign_group_text(AuthUser(user_id, _): AuthUser, Path((id, text_id)): Path<(i32, i32)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id).or_else(|| missing_assignment(&mut client, id, text_id)) {
+            return error;
+        }
+
+        groups::unassign_text(&mut client, id, text_id).unwrap();
+
+        json!({ "success": true })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+async fn group_text_report(AuthUser(user_id, _): AuthUser, Path((id, text_id)): Path<(i32, i32)>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        if let Some(error) = not_teacher(&mut client, user_id, id).or_else(|| missing_assignment(&mut client, id, text_id)) {
+            return error;
+        }
+
+        let text = texts::get_text(&mut client, text_id).unwrap().unwrap();
+        let report = compute_class_report(&mut client, id, &text.language, &text.content).unwrap();
+
+        json!({
+            "success": true,
+            "text_id": text.id,
+            "title": text.title,
+            "language": text.language,
+            "report": report
+        })
+    }).await.unwrap();
+
+    Json(response_json)
 }
 
 #[derive(Deserialize)]
 struct Text {
-    user_id: i32,
     text: String,
-    language: String,
+    language: Option<String>,
+    list: Option<i32>,
 }
 
-async fn analyse_text(Json(payload): Json<Text>) -> Json<serde_json::Value> {
+async fn analyse_text(AuthUser(user_id, _): AuthUser, Json(payload): Json<Text>) -> Json<serde_json::Value> {
     let result = task::spawn_blocking(move || {
+        let (language, warning) = match langdetect::resolve_language(payload.language.as_deref(), &payload.text) {
+            Ok(resolved) => resolved,
+            Err(message) => return json!({"success": false, "message": message}),
+        };
+
         let db_url = env::var("DATABASE_URL").unwrap();
         let mut client = Client::connect(&db_url, NoTls).unwrap();
 
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        if let Some(error) = payload.list.and_then(|list_id| missing_list(&mut client, user_id, list_id)) {
+            return error;
+        }
+
+        let text_id = texts::find_or_insert_text(&mut client, user_id, &language, &payload.text).unwrap();
+
         let freq = frequency_counter_from_text(&payload.text);
         let words: Vec<String> = freq.into_iter().map(|(word, _)| word).collect();
-        let known_user_word_ids: Vec<i32> = vocab_user::get_words_for_user(&mut client, payload.user_id).unwrap();
-        let known_user_words: Vec<String> = known_user_word_ids
-            .into_iter()
-            .filter_map(|id| words::get_text_word(&mut client, id).ok().flatten())
-            .collect();
-        let unknown_words: Vec<String> = words
+        let unknown_words = compute_coverage(&mut client, user_id, &language, &payload.text).unwrap().unknown_words;
+        let unknown: HashSet<&String> = unknown_words.iter().collect();
+        let known_words: Vec<String> = words
             .iter()
-            .filter(|word| !known_user_words.contains(word))
+            .filter(|word| !unknown.contains(word))
             .cloned()
             .collect();
-        let known_words: Vec<String> = known_user_words
+
+        words::insert_multiple_words(&mut client, &unknown_words, &language).unwrap();
+
+        let native_language = profiles::get_profile(&mut client, user_id, &language)
+            .unwrap()
+            .and_then(|profile| profile.native_language);
+        let glosses: HashMap<String, String> = dictionaries::get_glosses(&mut client, &language, native_language.as_deref(), &unknown_words)
+            .unwrap()
             .into_iter()
-            .filter(|word| words.contains(word))
+            .map(|(word, gloss)| (word, dictionary::short_gloss(&gloss)))
             .collect();
 
-        let new_words = words::insert_multiple_words(&mut client, &unknown_words, &payload.language).unwrap();
+        let annotations = annotations::get_annotations_among(&mut client, user_id, &language, &words).unwrap();
+        let lists = word_lists::get_subscribed_memberships(&mut client, user_id, &language, &words).unwrap();
 
         let mut word_status = HashMap::new();
 
@@ -83,7 +2162,18 @@ async fn analyse_text(Json(payload): Json<Text>) -> Json<serde_json::Value> {
             word_status.entry(word).or_insert("unknown");
         }
 
-        serde_json::json!({"words": word_status})
+        // With a list, only the text's words that belong to it are reported.
+        if let Some(list_id) = payload.list {
+            let members: HashSet<String> = word_lists::get_list_words(&mut client, user_id, list_id)
+                .unwrap()
+                .into_iter()
+                .filter(|entry| entry.language == language)
+                .map(|entry| entry.word)
+                .collect();
+            word_status.retain(|word, _| members.contains(word));
+        }
+
+        serde_json::json!({"text_id": text_id, "language": language, "warning": warning, "words": word_status, "glosses": glosses, "annotations": annotations, "lists": lists})
     }).await.unwrap();
 
     Json(result)
@@ -91,54 +2181,234 @@ async fn analyse_text(Json(payload): Json<Text>) -> Json<serde_json::Value> {
 
 #[derive(Deserialize)]
 struct Update {
-    user_id: i32,
     word: String,
     language: String,
     status: String,
 }
 
-async fn toggle_word_knowledge(Json(payload): Json<Update>) -> Json<serde_json::Value>{
+async fn toggle_word_knowledge(AuthUser(user_id, _): AuthUser, Json(payload): Json<Update>) -> Json<serde_json::Value> {
     let response_json = task::spawn_blocking(move || {
         let db_url = env::var("DATABASE_URL").unwrap();
         let mut client = Client::connect(&db_url, NoTls).unwrap();
 
-        let word = payload.word;
         let status = payload.status;
         let language = payload.language;
-        let user_id = payload.user_id;
 
-        let word_id_opt = words::get_id_word(&mut client, &word, &language).unwrap();
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        let Some(word) = normalize_word(&payload.word) else {
+            return json!({
+                "success": false,
+                "message": format!("Invalid word: '{}'", payload.word)
+            });
+        };
+
+        if status != "known" && status != "unknown" {
+            return json!({
+                "success": false,
+                "message": format!("Invalid status: {}", status)
+            });
+        }
+
+        let word_id = words::insert_word(&mut client, &word, &language).unwrap();
+
+        if status == "known" {
+            vocab_user::insert_vocab_user(&mut client, user_id, word_id).unwrap();
+        } else {
+            vocab_user::delete_vocab_user(&mut client, user_id, word_id).unwrap();
+        }
+
+        json!({
+            "success": true,
+            "message": format!("Word '{}' marked as {}", word, status),
+            "word": word,
+            "status": status
+        })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+
+#[derive(Deserialize)]
+struct WordOperation {
+    word: String,
+    language: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+struct BatchUpdate {
+    operations: Vec<WordOperation>,
+}
+
+async fn toggle_words_knowledge(AuthUser(user_id, _): AuthUser, Json(payload): Json<BatchUpdate>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let operations = payload.operations;
+        let known_languages: HashSet<String> = languages::get_languages(&mut client)
+            .unwrap()
+            .into_iter()
+            .map(|language| language.code)
+            .collect();
+
+        let normalized_words: Vec<Option<String>> = operations
+            .iter()
+            .map(|operation| normalize_word(&operation.word))
+            .collect();
+
+        let valid_status = |status: &str| status == "known" || status == "unknown";
+
+        // Only operations that will be applied may create words.
+        let mut words_by_language: HashMap<&str, Vec<String>> = HashMap::new();
+        for (operation, word) in operations.iter().zip(&normalized_words) {
+            let applies = known_languages.contains(&operation.language) && valid_status(&operation.status);
+            if let Some(word) = word.as_ref().filter(|_| applies) {
+                words_by_language
+                    .entry(operation.language.as_str())
+                    .or_default()
+                    .push(word.clone());
+            }
+        }
+
+        let mut transaction = client.transaction().unwrap();
 
-        if let Some(word_id) = word_id_opt {
-            match status.as_str() {
-                "known" => {
-                    vocab_user::insert_vocab_user(&mut client, user_id, word_id).unwrap();
+        let mut word_ids: HashMap<(&str, String), i32> = HashMap::new();
+        for (language, word_list) in words_by_language {
+            words::insert_multiple_words(&mut transaction, &word_list, language).unwrap();
+            let ids = words::get_id_words(&mut transaction, &word_list, language).unwrap();
+            for (word, id) in word_list.into_iter().zip(ids) {
+                if let Some(id) = id {
+                    word_ids.insert((language, word), id);
                 }
-                "unknown" => {
-                    vocab_user::delete_vocab_user(&mut client, user_id, word_id).unwrap();
+            }
+        }
+
+        let mut results = Vec::with_capacity(operations.len());
+        for (operation, word) in operations.iter().zip(normalized_words) {
+            let language = &operation.language;
+            let status = &operation.status;
+
+            let Some(word) = word else {
+                results.push(json!({
+                    "success": false,
+                    "word": operation.word,
+                    "message": format!("Invalid word: '{}'", operation.word)
+                }));
+                continue;
+            };
+
+            if !known_languages.contains(language) {
+                results.push(json!({
+                    "success": false,
+                    "word": word,
+                    "message": format!("Unknown language code: {}", language)
+                }));
+                continue;
+            }
+
+            if !valid_status(status) {
+                results.push(json!({
+                    "success": false,
+                    "word": word,
+                    "message": format!("Invalid status: {}", status)
+                }));
+                continue;
+            }
+
+            let word_id = word_ids[&(language.as_str(), word.clone())];
+
+            if status == "known" {
+                vocab_user::insert_vocab_user(&mut transaction, user_id, word_id).unwrap();
+            } else {
+                vocab_user::delete_vocab_user(&mut transaction, user_id, word_id).unwrap();
+            }
+
+            results.push(json!({
+                "success": true,
+                "word": word,
+                "status": status
+            }));
+        }
+
+        transaction.commit().unwrap();
+
+        json!({ "results": results })
+    }).await.unwrap();
+
+    Json(response_json)
+}
+
+#[derive(Deserialize)]
+struct MarkAllKnown {
+    language: String,
+    words: Option<Vec<String>>,
+    text_id: Option<i32>,
+}
+
+async fn mark_all_known(AuthUser(user_id, _): AuthUser, Json(payload): Json<MarkAllKnown>) -> Json<serde_json::Value> {
+    let response_json = task::spawn_blocking(move || {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        let mut client = Client::connect(&db_url, NoTls).unwrap();
+
+        let language = payload.language;
+
+        if let Some(error) = unknown_language(&mut client, &language) {
+            return error;
+        }
+
+        let mut rejected = Vec::new();
+        let mut word_list: Vec<String> = match (payload.words, payload.text_id) {
+            (Some(word_list), None) => {
+                let (valid, invalid) = split_words(word_list);
+                rejected = invalid;
+                valid
+            }
+            (None, Some(text_id)) => match texts::get_text(&mut client, text_id).unwrap() {
+                Some(text) if text.id_user == user_id && text.language == language => {
+                    frequency_counter_from_text(&text.content).into_iter().map(|(word, _)| word).collect()
                 }
                 _ => {
                     return json!({
                         "success": false,
-                        "message": format!("Invalid status: {}", status)
+                        "message": format!("Text {} not found in language '{}'", text_id, language)
                     });
                 }
+            },
+            _ => {
+                return json!({
+                    "success": false,
+                    "message": "Provide either 'words' or 'text_id'"
+                });
             }
+        };
 
-            json!({
-                "success": true,
-                "message": format!("Word '{}' marked as {}", word, status),
-                "word": word,
-                "status": status
-            })
-        } else {
-            json!({
-                "success": false,
-                "message": format!("Word '{}' not found in language '{}'", word, language)
-            })
-        }
+        let mut seen = HashSet::new();
+        word_list.retain(|word| seen.insert(word.clone()));
+
+        let mut transaction = client.transaction().unwrap();
+
+        words::insert_multiple_words(&mut transaction, &word_list, &language).unwrap();
+        let word_ids: Vec<i32> = words::get_id_words(&mut transaction, &word_list, &language)
+            .unwrap()
+            .into_iter()
+            .flatten()
+            .collect();
+        let changed = vocab_user::insert_vocab_users_multiple(&mut transaction, user_id, &word_ids).unwrap();
+
+        transaction.commit().unwrap();
+
+        json!({
+            "success": true,
+            "changed": changed,
+            "unchanged": word_ids.len() as u64 - changed,
+            "rejected": rejected
+        })
     }).await.unwrap();
 
     Json(response_json)
 }
-
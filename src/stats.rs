@@ -0,0 +1,226 @@
+use chrono::{Datelike, Days, NaiveDate};
+use postgres::{GenericClient, Error};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use crate::db::{profiles, vocab_user};
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct LanguageStats {
+    pub language: String,
+    pub known: i64,
+    pub daily_goal: Option<i32>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PeriodCount {
+    pub start: NaiveDate,
+    pub learned: i64,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Stats {
+    pub languages: Vec<LanguageStats>,
+    pub daily_goal: i32,
+    pub learned_today: i64,
+    pub per_day: Vec<PeriodCount>,
+    pub per_week: Vec<PeriodCount>,
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+pub fn compute_stats(client: &mut impl GenericClient, id_user: i32, language: Option<&str>, today: NaiveDate, days: u32) -> Result<Stats, Error> {
+    let user_profiles = profiles::get_profiles_for_user(client, id_user)?;
+    let goals: HashMap<String, i32> = user_profiles
+        .into_iter()
+        .filter(|profile| language.is_none_or(|language| profile.language == language))
+        .map(|profile| (profile.language, profile.daily_goal))
+        .collect();
+
+    let mut known: BTreeMap<String, i64> = vocab_user::count_known_by_language(client, id_user)?
+        .into_iter()
+        .filter(|(code, _)| language.is_none_or(|language| code == language))
+        .collect();
+    for code in goals.keys() {
+        known.entry(code.clone()).or_insert(0);
+    }
+
+    let languages = known
+        .into_iter()
+        .map(|(code, known)| LanguageStats {
+            daily_goal: goals.get(&code).copied(),
+            language: code,
+            known,
+        })
+        .collect();
+
+    let daily_goal: i32 = goals.values().sum();
+    let learned: BTreeMap<NaiveDate, i64> = vocab_user::count_learned_per_day(client, id_user, language)?
+        .into_iter()
+        .collect();
+
+    let first_day = today - Days::new(u64::from(days.max(1)) - 1);
+    let per_day = first_day
+        .iter_days()
+        .take_while(|day| *day <= today)
+        .map(|day| PeriodCount {
+            start: day,
+            learned: learned.get(&day).copied().unwrap_or(0),
+        })
+        .collect();
+
+    let mut weeks: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut week = week_start(first_day);
+    while week <= today {
+        weeks.insert(week, 0);
+        week = week + Days::new(7);
+    }
+    for (day, count) in &learned {
+        if let Some(total) = weeks.get_mut(&week_start(*day)) {
+            *total += count;
+        }
+    }
+    let per_week = weeks
+        .into_iter()
+        .map(|(start, learned)| PeriodCount { start, learned })
+        .collect();
+
+    let threshold = i64::from(daily_goal.max(1));
+    let active_days: Vec<NaiveDate> = learned
+        .iter()
+        .filter(|(_, count)| **count >= threshold)
+        .map(|(day, _)| *day)
+        .collect();
+    let (current_streak, longest_streak) = streaks(&active_days, today);
+
+    Ok(Stats {
+        languages,
+        daily_goal,
+        learned_today: learned.get(&today).copied().unwrap_or(0),
+        per_day,
+        per_week,
+        current_streak,
+        longest_streak,
+    })
+}
+
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - Days::new(u64::from(day.weekday().num_days_from_monday()))
+}
+
+// The current streak still counts when today has no activity yet, as long
+// as yesterday did.
+pub fn streaks(active_days: &[NaiveDate], today: NaiveDate) -> (u32, u32) {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+
+    for day in active_days {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(*day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+
+    let yesterday = today.pred_opt();
+    let current = match previous {
+        Some(last) if last == today || Some(last) == yesterday => run,
+        _ => 0,
+    };
+
+    (current, longest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, Utc};
+    use postgres::{Client, NoTls};
+    use std::env;
+    use crate::db::users::insert_user;
+    use crate::db::words::insert_word;
+
+    fn connect_test_client() -> Client {
+        let db_url = env::var("DATABASE_URL").unwrap();
+        Client::connect(&db_url, NoTls).unwrap()
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_streaks() {
+        let days = [date("2024-01-01"), date("2024-01-02"), date("2024-01-03"), date("2024-01-06"), date("2024-01-07")];
+
+        assert_eq!(streaks(&days, date("2024-01-07")), (2, 3));
+        assert_eq!(streaks(&days, date("2024-01-08")), (2, 3));
+        assert_eq!(streaks(&days, date("2024-01-09")), (0, 3));
+        assert_eq!(streaks(&[], date("2024-01-09")), (0, 0));
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        profiles::upsert_profile(&mut client, user_id, "de", Some("pt"), 2).unwrap();
+
+        let mut entries = Vec::new();
+        for (index, (word, day)) in [("eins", "2024-01-08"), ("zwei", "2024-01-08"), ("drei", "2024-01-09"), ("vier", "2024-01-10"), ("fünf", "2024-01-10")].iter().enumerate() {
+            let id = insert_word(&mut client, word, "de").unwrap();
+            let at = DateTime::parse_from_rfc3339(&format!("{}T12:00:0{}Z", day, index)).unwrap().with_timezone(&Utc);
+            entries.push((id, Some(at)));
+        }
+        let cat_id = insert_word(&mut client, "cat", "en").unwrap();
+        entries.push((cat_id, Some(DateTime::parse_from_rfc3339("2024-01-01T12:00:00Z").unwrap().with_timezone(&Utc))));
+        vocab_user::insert_vocab_users_with_dates(&mut client, user_id, &entries).unwrap();
+
+        let stats = compute_stats(&mut client, user_id, None, date("2024-01-10"), 10).unwrap();
+        assert_eq!(stats.languages, vec![
+            LanguageStats { language: "de".to_string(), known: 5, daily_goal: Some(2) },
+            LanguageStats { language: "en".to_string(), known: 1, daily_goal: None },
+        ]);
+        assert_eq!(stats.daily_goal, 2);
+        assert_eq!(stats.learned_today, 2);
+        assert_eq!(stats.per_day.len(), 10);
+        assert_eq!(stats.per_day[0], PeriodCount { start: date("2024-01-01"), learned: 1 });
+        assert_eq!(stats.per_week, vec![
+            PeriodCount { start: date("2024-01-01"), learned: 1 },
+            PeriodCount { start: date("2024-01-08"), learned: 5 },
+        ]);
+        assert_eq!((stats.current_streak, stats.longest_streak), (1, 1));
+
+        let german = compute_stats(&mut client, user_id, Some("de"), date("2024-01-10"), 3).unwrap();
+        assert_eq!(german.languages.len(), 1);
+        assert_eq!(german.per_day.len(), 3);
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+
+    #[test]
+    fn test_compute_stats_skips_undated_words() {
+        let mut client = connect_test_client();
+        client.batch_execute("BEGIN").unwrap();
+
+        let user_id = insert_user(&mut client, "testuser").unwrap();
+        let mut entries = Vec::new();
+        for word in ["legacy1", "legacy2", "legacy3"] {
+            entries.push((insert_word(&mut client, word, "en").unwrap(), None));
+        }
+        let today = Utc::now();
+        entries.push((insert_word(&mut client, "recent", "en").unwrap(), Some(today)));
+        vocab_user::insert_vocab_users_with_dates(&mut client, user_id, &entries).unwrap();
+
+        let stats = compute_stats(&mut client, user_id, None, today.date_naive(), 7).unwrap();
+        assert_eq!(stats.languages[0].known, 4);
+        assert_eq!(stats.learned_today, 1);
+        assert_eq!(stats.per_day.iter().map(|day| day.learned).sum::<i64>(), 1);
+        assert_eq!(stats.per_week.iter().map(|week| week.learned).sum::<i64>(), 1);
+        assert_eq!((stats.current_streak, stats.longest_streak), (1, 1));
+
+        client.batch_execute("ROLLBACK").unwrap();
+    }
+}
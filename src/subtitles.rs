@@ -0,0 +1,175 @@
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashMap;
+use crate::tokenizer::tokenize;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SubtitleFormat {
+    Srt,
+    Vtt,
+}
+
+impl SubtitleFormat {
+    pub fn detect(content: &str) -> SubtitleFormat {
+        if content.trim_start_matches('\u{feff}').starts_with("WEBVTT") {
+            SubtitleFormat::Vtt
+        } else {
+            SubtitleFormat::Srt
+        }
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+impl Cue {
+    pub fn start(&self) -> String {
+        format_timestamp(self.start_ms)
+    }
+}
+
+pub fn parse_subtitles(content: &str) -> Vec<Cue> {
+    let format = SubtitleFormat::detect(content);
+    let markup = Regex::new(r"<[^>]*>|\{[^}]*\}").unwrap();
+    let content = content.trim_start_matches('\u{feff}').replace("\r\n", "\n").replace('\r', "\n");
+
+    let mut cues = Vec::new();
+    for block in content.split("\n\n") {
+        let lines: Vec<&str> = block.lines().collect();
+        let Some(timing_index) = lines.iter().position(|line| line.contains("-->")) else {
+            continue;
+        };
+        if format == SubtitleFormat::Vtt && lines[0].starts_with("NOTE") {
+            continue;
+        }
+        let Some((start_ms, end_ms)) = parse_timing(lines[timing_index]) else {
+            continue;
+        };
+
+        let text = lines[timing_index + 1..]
+            .iter()
+            .map(|line| {
+                let line = markup.replace_all(line, "");
+                match format {
+                    SubtitleFormat::Vtt => decode_entities(&line),
+                    SubtitleFormat::Srt => line.into_owned(),
+                }
+            })
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        if !text.is_empty() {
+            cues.push(Cue { start_ms, end_ms, text });
+        }
+    }
+
+    cues
+}
+
+pub fn cues_text(cues: &[Cue]) -> String {
+    cues.iter().map(|cue| cue.text.as_str()).collect::<Vec<_>>().join("\n")
+}
+
+pub fn timestamps_by_word(cues: &[Cue]) -> HashMap<String, Vec<String>> {
+    let mut timestamps: HashMap<String, Vec<String>> = HashMap::new();
+
+    for cue in cues {
+        let mut words = tokenize(&cue.text);
+        words.sort();
+        words.dedup();
+        for word in words {
+            timestamps.entry(word).or_default().push(cue.start());
+        }
+    }
+
+    timestamps
+}
+
+fn parse_timing(line: &str) -> Option<(u64, u64)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_timestamp(start.trim())?, parse_timestamp(end)?))
+}
+
+// Both formats write milliseconds with exactly three digits.
+fn parse_timestamp(timestamp: &str) -> Option<u64> {
+    let (clock, millis) = timestamp.rsplit_once([',', '.'])?;
+    if millis.len() != 3 || !millis.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    let millis: u64 = millis.parse().ok()?;
+
+    let mut seconds: u64 = 0;
+    for part in clock.split(':') {
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+    }
+
+    seconds.checked_mul(1000)?.checked_add(millis)
+}
+
+fn format_timestamp(ms: u64) -> String {
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, ms % 1000)
+}
+
+fn decode_entities(line: &str) -> String {
+    line.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&lrm;", "")
+        .replace("&rlm;", "")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_srt() {
+        let content = "\u{feff}1\r\n00:00:01,000 --> 00:00:02,500\r\n<i>Hello</i> there!\r\n\r\n2\r\n00:01:03,250 --> 00:01:05,000\r\n{\\an8}- Where are you?\r\n- Home.\r\n";
+        let cues = parse_subtitles(content);
+        assert_eq!(cues, vec![
+            Cue { start_ms: 1000, end_ms: 2500, text: "Hello there!".to_string() },
+            Cue { start_ms: 63250, end_ms: 65000, text: "- Where are you?\n- Home.".to_string() },
+        ]);
+        assert_eq!(cues[1].start(), "00:01:03.250");
+    }
+
+    #[test]
+    fn test_parse_vtt() {
+        let content = "WEBVTT\n\nNOTE this is a comment --> not a cue\n\nSTYLE\n::cue { color: yellow }\n\nintro\n00:01.000 --> 00:02.000 align:start\n<v Bob>Tom &amp; Jerry</v>\n\n01:00:00.000 --> 01:00:01.000\n<c.yellow>Bye</c>\n";
+        let cues = parse_subtitles(content);
+        assert_eq!(cues, vec![
+            Cue { start_ms: 1000, end_ms: 2000, text: "Tom & Jerry".to_string() },
+            Cue { start_ms: 3_600_000, end_ms: 3_601_000, text: "Bye".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(parse_timestamp("01:02:03,456"), Some(3_723_456));
+        assert_eq!(parse_timestamp("02:03.004"), Some(123_004));
+        assert_eq!(parse_timestamp("00:00:01,5"), None);
+        assert_eq!(parse_timestamp("00:00:01,5000"), None);
+        assert_eq!(parse_timestamp("9999999999999999:00:00,000"), None);
+        assert_eq!(parse_timestamp("99999999999999999999:00:00,000"), None);
+    }
+
+    #[test]
+    fn test_timestamps_by_word() {
+        let cues = vec![
+            Cue { start_ms: 1000, end_ms: 2000, text: "The cat, the cat.".to_string() },
+            Cue { start_ms: 5000, end_ms: 6000, text: "A cat!".to_string() },
+        ];
+        let timestamps = timestamps_by_word(&cues);
+        assert_eq!(timestamps["cat"], vec!["00:00:01.000", "00:00:05.000"]);
+        assert_eq!(timestamps["the"], vec!["00:00:01.000"]);
+        assert_eq!(cues_text(&cues), "The cat, the cat.\nA cat!");
+    }
+}
@@ -2,13 +2,16 @@ use regex::Regex;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::collections::HashMap;
+use std::cmp::Reverse;
+
+const WORD_PATTERN: &str = r"[\p{L}\p{N}]+(?:['+][\p{L}\p{N}]+)*";
 
 pub fn frequency_counter(file_url: &str) -> io::Result<Vec<(String, u32)>> {
     let file = File::open(file_url)?;
     let reader = BufReader::new(file);
 
     let mut freq = HashMap::new();
-    let re = Regex::new(r"[\p{L}\p{N}]+(?:['+][\p{L}\p{N}]+)*").unwrap();
+    let re = Regex::new(WORD_PATTERN).unwrap();
 
     for line in reader.lines() {
         let line = line?.replace('’', "'");
@@ -19,14 +22,14 @@ pub fn frequency_counter(file_url: &str) -> io::Result<Vec<(String, u32)>> {
     }
 
     let mut items: Vec<(String, u32)> = freq.into_iter().collect();
-    items.sort_by(|a, b| b.1.cmp(&a.1));
+    items.sort_by_key(|item| Reverse(item.1));
 
     Ok(items)
 }
 
 pub fn frequency_counter_from_text(text: &str) -> Vec<(String, u32)> {
     let mut freq = HashMap::new();
-    let re = Regex::new(r"[\p{L}\p{N}]+(?:['+][\p{L}\p{N}]+)*").unwrap();
+    let re = Regex::new(WORD_PATTERN).unwrap();
 
     for line in text.lines() {
         let line = line.replace('’', "'");
@@ -37,6 +40,65 @@ pub fn frequency_counter_from_text(text: &str) -> Vec<(String, u32)> {
     }
 
     let mut items: Vec<(String, u32)> = freq.into_iter().collect();
-    items.sort_by(|a, b| b.1.cmp(&a.1));
+    items.sort_by_key(|item| Reverse(item.1));
     items
 }
+
+pub fn tokenize(text: &str) -> Vec<String> {
+    let re = Regex::new(WORD_PATTERN).unwrap();
+    let text = text.replace('’', "'");
+
+    re.find_iter(&text).map(|word| word.as_str().to_lowercase()).collect()
+}
+
+pub fn split_sentences(text: &str) -> Vec<&str> {
+    let re = Regex::new(r"[^.!?…\n]+[.!?…]*").unwrap();
+
+    re.find_iter(text)
+        .map(|sentence| sentence.as_str().trim())
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+pub fn normalize_word(word: &str) -> Option<String> {
+    let re = Regex::new(WORD_PATTERN).unwrap();
+    let word = word.trim().replace('’', "'");
+
+    let token = re.find(&word)?;
+    if token.start() != 0 || token.end() != word.len() {
+        return None;
+    }
+
+    Some(word.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("The cat’s hat, the CAT."), vec!["the", "cat's", "hat", "the", "cat"]);
+    }
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = split_sentences("Hello there. How are you?\nFine!");
+        assert_eq!(sentences, vec!["Hello there.", "How are you?", "Fine!"]);
+    }
+
+    #[test]
+    fn test_normalize_word() {
+        assert_eq!(normalize_word("Cat"), Some("cat".to_string()));
+        assert_eq!(normalize_word("  don’t "), Some("don't".to_string()));
+        assert_eq!(normalize_word("São"), Some("são".to_string()));
+    }
+
+    #[test]
+    fn test_normalize_word_rejects_junk() {
+        assert_eq!(normalize_word(""), None);
+        assert_eq!(normalize_word("two words"), None);
+        assert_eq!(normalize_word("cat!"), None);
+        assert_eq!(normalize_word("'cat"), None);
+    }
+}